@@ -0,0 +1,123 @@
+// Hierarchical wind sway for plant meshes: the whole plant bends about its root,
+// each branch swings about its pivot, and leaves flutter along their normal.
+#import bevy_pbr::{
+    mesh_functions,
+    skinning,
+    forward_io::VertexOutput,
+    mesh_view_bindings::globals,
+    view_transformations::position_world_to_clip,
+}
+#import bevy_render::maths::inverse_mat3x3
+
+struct Wind {
+    direction: vec3<f32>,
+    strength: f32,
+    gustiness: f32,
+    flutter: f32,
+    height: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> wind: Wind;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_UVS_A
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_UVS_B
+    @location(3) uv_b: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(4) tangent: vec4<f32>,
+#endif
+#ifdef VERTEX_COLORS
+    @location(5) color: vec4<f32>,
+#endif
+#ifdef SKINNED
+    @location(6) joint_indices: vec4<u32>,
+    @location(7) joint_weights: vec4<f32>,
+#endif
+    @location(8) branch_depth: f32,
+    @location(9) branch_distance: f32,
+    @location(10) branch_pivot: vec3<f32>,
+    @location(11) branch_phase: f32,
+};
+
+// Gusts travel across the world along the wind direction
+fn gust(world_position: vec3<f32>, t: f32) -> f32 {
+    let along = dot(world_position.xz, wind.direction.xz) * 0.004;
+    let wave = sin(t * 0.7 - along) * sin(t * 1.9 - along * 2.3);
+    return max(1.0 + wind.gustiness * wave, 0.0);
+}
+
+// The wind blows in world space, the vertex moves in the mesh's own space
+fn sway(vertex: Vertex, world_origin: vec3<f32>, local_from_world: mat3x3<f32>) -> vec3<f32> {
+    let t = globals.time;
+    let strength = wind.strength * gust(world_origin + vertex.branch_pivot, t);
+    let dir = local_from_world * wind.direction;
+    let up = vec3<f32>(0.0, 1.0, 0.0);
+    var position = vertex.position;
+
+    // Leaves flutter fast, scaled by distance from where they attach
+    let flutter = sin(t * 9.0 + vertex.branch_phase * 3.0 + vertex.branch_distance * 0.3);
+    position += vertex.normal * wind.flutter * strength * 0.02 * flutter * vertex.branch_distance;
+
+    // Branches swing about their pivot; deeper branches move more
+    let depth = min(vertex.branch_depth, 4.0);
+    let swing = strength * 0.03 * depth * (0.6 + 0.4 * sin(t * 2.1 + vertex.branch_phase));
+    let axis = normalize(cross(up, dir));
+    position += cross(axis, position - vertex.branch_pivot) * swing;
+
+    // Trunk bends towards the wind, quadratically with height
+    let h = clamp(position.y / max(wind.height, 0.001), 0.0, 1.0);
+    let bend = strength * 0.04 * (0.8 + 0.2 * sin(t * 0.9));
+    position += dir * wind.height * bend * h * h;
+    return position;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let mesh_world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+#ifdef SKINNED
+    let world_from_local = skinning::skin_model(vertex.joint_indices, vertex.joint_weights, vertex.instance_index);
+    out.world_normal = skinning::skin_normals(world_from_local, vertex.normal);
+#else
+    let world_from_local = mesh_world_from_local;
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, vertex.instance_index);
+#endif
+
+    let local_from_world = inverse_mat3x3(mat3x3<f32>(
+        world_from_local[0].xyz,
+        world_from_local[1].xyz,
+        world_from_local[2].xyz,
+    ));
+    let position = sway(vertex, mesh_world_from_local[3].xyz, local_from_world);
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(position, 1.0));
+    out.position = position_world_to_clip(out.world_position.xyz);
+
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_UVS_B
+    out.uv_b = vertex.uv_b;
+#endif
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(world_from_local, vertex.tangent, vertex.instance_index);
+#endif
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index, mesh_world_from_local[3]);
+#endif
+
+    return out;
+}
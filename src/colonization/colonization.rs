@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use bevy::mesh::VertexAttributeValues;
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::material::SurfaceKind;
+use crate::palette::Palette;
+use crate::plant::Plant;
+use crate::skeleton::{Folio, PlantSkeleton, SkeletonNode};
+
+/// What builds a plant's branch graph
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Generator {
+    /// Interpret the L-system string with the turtle
+    #[default]
+    LSystem,
+    /// Grow towards an attractor cloud (Runions et al. 2007); the string is not rewritten
+    Colonization(ColonizationSettings),
+}
+
+/// Region filled with attractor points
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum AttractorVolume {
+    Sphere { center: Vec3, radius: f32 },
+    Ellipsoid { center: Vec3, radii: Vec3 },
+    /// Inside of a closed mesh asset, e.g. `"crowns/oak.glb#Mesh0/Primitive0"`
+    Mesh { path: String },
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColonizationSettings {
+    pub volume: AttractorVolume,
+    pub attractors: usize,
+    /// Attractors this close to a node are reached and removed
+    pub kill_distance: f32,
+    /// Attractors further than this from every node don't pull
+    pub influence_radius: f32,
+    /// Segment length
+    pub step: f32,
+    /// Growth steps per plant iteration, so the timeline animates the colonization
+    pub steps_per_iteration: usize,
+    /// Hang a leaf off every branch tip
+    pub leaves: bool,
+}
+
+impl Default for ColonizationSettings {
+    fn default() -> Self {
+        Self {
+            volume: AttractorVolume::Ellipsoid { center: Vec3::new(0.0, 120.0, 0.0), radii: Vec3::new(70.0, 50.0, 70.0) },
+            attractors: 600,
+            kill_distance: 12.0,
+            influence_radius: 60.0,
+            step: 6.0,
+            steps_per_iteration: 8,
+            leaves: true,
+        }
+    }
+}
+
+/// Keeps attractor meshes loaded, by path
+#[derive(Resource, Default)]
+pub struct VolumeMeshes(pub HashMap<String, Handle<Mesh>>);
+
+impl VolumeMeshes {
+    pub fn get<'a>(&self, plant: &Plant, meshes: &'a Assets<Mesh>) -> Option<&'a Mesh> {
+        match &plant.generator {
+            Generator::Colonization(ColonizationSettings { volume: AttractorVolume::Mesh { path }, .. }) => {
+                meshes.get(self.0.get(path)?)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Start loading attractor meshes as plants ask for them, redraw those plants once loaded and
+/// let go of meshes no plant uses any more
+pub fn load_volume_meshes(
+    asset_server: Res<AssetServer>,
+    mut volumes: ResMut<VolumeMeshes>,
+    mut events: MessageReader<AssetEvent<Mesh>>,
+    mut plants: Query<&mut Plant>,
+) {
+    let loaded: Vec<AssetId<Mesh>> = events.read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    let mut used = Vec::new();
+    for mut plant in &mut plants {
+        let Generator::Colonization(ColonizationSettings { volume: AttractorVolume::Mesh { path }, .. }) = &plant.generator else {
+            continue;
+        };
+        if path.is_empty() {
+            continue;
+        }
+        let handle = volumes.0.entry(path.clone()).or_insert_with(|| asset_server.load(path.clone()));
+        used.push(path.clone());
+        if loaded.contains(&handle.id()) {
+            plant.set_changed();
+        }
+    }
+    volumes.0.retain(|path, _| used.contains(path));
+}
+
+/// Grow `steps` colonization steps from a root at the origin. Deterministic for a `seed`.
+/// `volume_mesh` is the loaded mesh for `AttractorVolume::Mesh`; without it only the trunk grows.
+pub fn colonize(settings: &ColonizationSettings, seed: u64, steps: usize, root_radius: f32, palette: &Palette, volume_mesh: Option<&Mesh>) -> PlantSkeleton {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut attractors = sample_attractors(&settings.volume, settings.attractors, volume_mesh, &mut rng);
+    let step = settings.step.max(0.01);
+    let bark = palette.index(0);
+
+    let mut skeleton = PlantSkeleton::default();
+    let mut grid = NodeGrid::new(settings.influence_radius.max(step));
+    let grow = |skeleton: &mut PlantSkeleton, grid: &mut NodeGrid, parent: Option<usize>, start: Vec3, dir: Vec3| {
+        let (order, distance) = parent.map_or((0, 0.0), |p| {
+            let node = &skeleton.nodes[p];
+            // The first child continues its parent's axis, later ones are lateral branches
+            (node.order + u32::from(!node.children.is_empty()), node.distance + node.length())
+        });
+        let position = start + dir * step;
+        let index = skeleton.push(SkeletonNode {
+            start,
+            position,
+            frame: Quat::from_rotation_arc(Vec3::Y, dir),
+            radius: root_radius * 0.5,
+            color: bark,
+            depth: order,
+            order,
+            parent,
+            children: Vec::new(),
+            symbol: 0,
+            distance,
+        });
+        grid.insert(position, index);
+        index
+    };
+    // Tip of the bare trunk, until the first attractor comes in reach and branching starts
+    let mut trunk = Some(grow(&mut skeleton, &mut grid, None, Vec3::ZERO, Vec3::Y));
+
+    for _ in 0..steps {
+        if attractors.is_empty() {
+            break;
+        }
+
+        // Each attractor pulls on its closest node within the influence radius
+        let mut pull: HashMap<usize, Vec3> = HashMap::new();
+        for &a in &attractors {
+            if let Some(node) = grid.closest(a, &skeleton, settings.influence_radius) {
+                *pull.entry(node).or_default() += (a - skeleton.nodes[node].position).normalize_or_zero();
+            }
+        }
+
+        let mut grown = Vec::new();
+        if pull.is_empty() {
+            // Nothing in reach yet: extend the trunk straight up towards the crown. Once the
+            // crown has branched, what is left is out of every tip's reach
+            let Some(tip) = trunk else { break };
+            let start = skeleton.nodes[tip].position;
+            let next = grow(&mut skeleton, &mut grid, Some(tip), start, Vec3::Y);
+            trunk = Some(next);
+            grown.push(next);
+        } else {
+            trunk = None;
+            // Sorted so growth doesn't depend on hash order
+            let mut pull: Vec<(usize, Vec3)> = pull.into_iter().collect();
+            pull.sort_by_key(|(node, _)| *node);
+            for (node, sum) in pull {
+                let dir = sum.normalize_or_zero();
+                let start = skeleton.nodes[node].position;
+                // Two equidistant attractors can pull a node the same way forever
+                let repeats = skeleton.nodes[node].children.iter()
+                    .any(|&c| skeleton.nodes[c].position.distance_squared(start + dir * step) < 1e-4);
+                if dir != Vec3::ZERO && !repeats {
+                    grown.push(grow(&mut skeleton, &mut grid, Some(node), start, dir));
+                }
+            }
+            if grown.is_empty() {
+                break;
+            }
+        }
+
+        let tips: Vec<Vec3> = grown.iter().map(|&n| skeleton.nodes[n].position).collect();
+        remove_reached(&mut attractors, &tips, settings.kill_distance);
+    }
+
+    if settings.leaves {
+        let leaf = palette.index(1);
+        let size = step * 1.5;
+        for (i, node) in skeleton.nodes.iter().enumerate().filter(|(_, n)| n.children.is_empty()) {
+            let (side, up) = (node.frame * Vec3::X * size * 0.5, node.frame * Vec3::Y * size);
+            let base = node.position;
+            skeleton.folios.push(Folio {
+                vertices: vec![base, base + side + up * 0.5, base + up, base - side + up * 0.5],
+                color: leaf,
+                kind: SurfaceKind::Leaf,
+                parent: Some(i),
+            });
+        }
+    }
+
+    skeleton
+}
+
+/// Drop the attractors within `kill_distance` of a newly grown node
+fn remove_reached(attractors: &mut Vec<Vec3>, grown: &[Vec3], kill_distance: f32) {
+    let kill = kill_distance * kill_distance;
+    attractors.retain(|a| !grown.iter().any(|p| p.distance_squared(*a) < kill));
+}
+
+fn sample_attractors(volume: &AttractorVolume, count: usize, mesh: Option<&Mesh>, rng: &mut StdRng) -> Vec<Vec3> {
+    let (center, radii, inside): (Vec3, Vec3, Box<dyn Fn(Vec3) -> bool>) = match volume {
+        AttractorVolume::Sphere { center, radius } => (*center, Vec3::splat(*radius), Box::new(|p: Vec3| p.length_squared() <= 1.0)),
+        AttractorVolume::Ellipsoid { center, radii } => (*center, *radii, Box::new(|p: Vec3| p.length_squared() <= 1.0)),
+        AttractorVolume::Mesh { .. } => {
+            let Some(triangles) = mesh.and_then(mesh_triangles) else { return Vec::new() };
+            let (min, max) = triangles.iter().flatten()
+                .fold((Vec3::MAX, Vec3::MIN), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+            let (center, radii) = ((min + max) * 0.5, (max - min) * 0.5);
+            (center, radii, Box::new(move |p: Vec3| contains(&triangles, center + p * radii)))
+        }
+    };
+
+    // Rejection sampling in the unit cube, scaled to the bounds
+    let mut points = Vec::with_capacity(count);
+    for _ in 0..count * 20 {
+        if points.len() == count {
+            break;
+        }
+        let p = Vec3::new(rng.random_range(-1.0..=1.0), rng.random_range(-1.0..=1.0), rng.random_range(-1.0..=1.0));
+        if inside(p) {
+            points.push(center + p * radii);
+        }
+    }
+    points
+}
+
+fn mesh_triangles(mesh: &Mesh) -> Option<Vec<[Vec3; 3]>> {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return None;
+    };
+    let vertex = |i: usize| Vec3::from(positions[i]);
+    let triangles = match mesh.indices() {
+        Some(indices) => indices.iter().collect::<Vec<_>>()
+            .chunks_exact(3)
+            .map(|t| [vertex(t[0]), vertex(t[1]), vertex(t[2])])
+            .collect(),
+        None => (0..positions.len() / 3).map(|t| [vertex(t * 3), vertex(t * 3 + 1), vertex(t * 3 + 2)]).collect(),
+    };
+    Some(triangles)
+}
+
+/// Point-in-mesh by counting crossings of a ray along +X (the mesh must be closed)
+fn contains(triangles: &[[Vec3; 3]], p: Vec3) -> bool {
+    let crossings = triangles.iter().filter(|[a, b, c]| {
+        // Möller–Trumbore with direction +X
+        let (e1, e2) = (*b - *a, *c - *a);
+        let h = Vec3::X.cross(e2);
+        let det = e1.dot(h);
+        if det.abs() < 1e-8 {
+            return false;
+        }
+        let s = p - *a;
+        let u = s.dot(h) / det;
+        let q = s.cross(e1);
+        let v = Vec3::X.dot(q) / det;
+        u >= 0.0 && v >= 0.0 && u + v <= 1.0 && e2.dot(q) / det > 0.0
+    }).count();
+    crossings % 2 == 1
+}
+
+/// Nodes bucketed by cell so closest-node lookups only scan neighbouring cells
+struct NodeGrid {
+    cell: f32,
+    cells: HashMap<IVec3, Vec<usize>>,
+}
+
+impl NodeGrid {
+    fn new(cell: f32) -> Self {
+        Self { cell, cells: HashMap::new() }
+    }
+
+    fn key(&self, p: Vec3) -> IVec3 {
+        (p / self.cell).floor().as_ivec3()
+    }
+
+    fn insert(&mut self, p: Vec3, node: usize) {
+        let key = self.key(p);
+        self.cells.entry(key).or_default().push(node);
+    }
+
+    /// Closest node within `radius` (no more than one cell), lowest index on ties
+    fn closest(&self, p: Vec3, skeleton: &PlantSkeleton, radius: f32) -> Option<usize> {
+        let key = self.key(p);
+        let mut best: Option<(f32, usize)> = None;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let Some(nodes) = self.cells.get(&(key + IVec3::new(dx, dy, dz))) else { continue };
+                    for &n in nodes {
+                        let d = skeleton.nodes[n].position.distance_squared(p);
+                        if d <= radius * radius && best.is_none_or(|(bd, bn)| d < bd || (d == bd && n < bn)) {
+                            best = Some((d, n));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, n)| n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::ColorStop;
+
+    #[test]
+    fn colonization_is_seeded_and_kills_reached_attractors() {
+        let palette = Palette { stops: vec![ColorStop { position: 0.0, color: Color::WHITE }] };
+        let settings = ColonizationSettings { attractors: 300, ..default() };
+        let grow = |seed| colonize(&settings, seed, 60, 4.0, &palette, None);
+        let positions = |skeleton: &PlantSkeleton| skeleton.nodes.iter().map(|n| n.position).collect::<Vec<_>>();
+
+        let (a, b, c) = (grow(7), grow(7), grow(8));
+        assert!(a.nodes.len() > 20 && !a.folios.is_empty());
+        assert_eq!(positions(&a), positions(&b));
+        assert_ne!(positions(&a), positions(&c));
+        // The trunk climbs into the crown before branching
+        assert!(a.height() > 70.0);
+
+        let mut attractors = vec![Vec3::ZERO, Vec3::X * 5.0, Vec3::X * 11.9, Vec3::X * 12.0, Vec3::Y * 30.0];
+        remove_reached(&mut attractors, &[Vec3::ZERO, Vec3::Y * 100.0], settings.kill_distance);
+        assert_eq!(attractors, [Vec3::X * 12.0, Vec3::Y * 30.0]);
+    }
+}
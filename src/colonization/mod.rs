@@ -0,0 +1,3 @@
+pub mod colonization;
+
+pub use colonization::{colonize, load_volume_meshes, AttractorVolume, ColonizationSettings, Generator, VolumeMeshes};
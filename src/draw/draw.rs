@@ -1,12 +1,29 @@
 use bevy::{
     asset::RenderAssetUsages,
-    mesh::{Indices},
+    mesh::{Indices, VertexAttributeValues},
     prelude::*,
     render::render_resource::PrimitiveTopology,
 };
-use bevy_egui::egui::FontSelection;
 
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::f32::consts::{PI, TAU};
+
+use crate::draw::lod::{cull_thin_segments, merge_segments};
+use crate::impostor::{impostor_key, spawn_billboard, ImpostorBake, ImpostorCache};
+use crate::draw::thickness::{apply_pipe_model, ThicknessModel};
+use crate::lsystem::{query_module_at, AxisAngle};
+use crate::material::SurfaceKind;
+use crate::palette::ColorMode;
 use crate::plant::Plant;
+use crate::stats::PlantStats;
+use crate::light::ShadowCasters;
+use crate::colonization::VolumeMeshes;
+use std::time::Instant;
+use crate::skeleton::{rig::Rig, Folio, PlantSkeleton, QueryPoint, SkeletonNode};
+use bevy::mesh::skinning::SkinnedMeshInverseBindposes;
+use crate::wind::wind::{branch_sway, Sway, ATTRIBUTE_BRANCH_DEPTH, ATTRIBUTE_BRANCH_DISTANCE, ATTRIBUTE_BRANCH_PHASE, ATTRIBUTE_BRANCH_PIVOT};
+use crate::wind::{Wind, WindExtension, WindMaterial};
 
 #[derive(Clone, Copy)]
 struct Turtle3D {
@@ -14,151 +31,346 @@ struct Turtle3D {
     rot: Quat,
     thickness: f32,
     color_index: usize,
+    /// Node drawn most recently on this branch, parent of the next one
+    last_node: Option<usize>,
+    /// Path length travelled from the root
+    distance: f32,
+    /// Material for polygons started on this branch
+    surface: SurfaceKind,
+    /// Bracket scope the turtle is drawing in
+    branch: usize,
 }
 
-#[derive(Clone, Copy)]
-struct Segment{
-    start: Vec3,
-    end: Vec3,
-    thickness: f32,
-    color: Color,
-}
-
-#[derive(Clone)]
-struct Folio{
-    vertices: Vec<Vec3>,
-    color: Color,
-}
+/// Build the plant's branch graph; `birth` holds the creation iteration of each char, used by `ColorMode::Iteration`
+pub(crate) fn interpret_plant(lsystem_string: &str, birth: &[u16], plant: &Plant) -> PlantSkeleton {
+    let lsystem = &plant.lsystem;
+    let step_size = plant.step_size;
+    let root_thickness = plant.root_thickness;
+    let palette = &plant.palette;
 
-fn interpret_plant(lsystem_string: &str, step_size: f32, turn_angle_deg: f32, root_thickness: f32, palette: &Vec<Color>) -> (Vec<Segment>, Vec<Folio>) {
     let mut turtle = Turtle3D {
         pos: Vec3::ZERO,
         rot: Quat::IDENTITY, // facing +Y
         thickness: root_thickness,
         color_index: 0,
+        last_node: None,
+        distance: 0.0,
+        surface: SurfaceKind::Leaf,
+        branch: 0,
     };
+    let mut branches = 0;
     let mut stack = Vec::new();
-    let mut segments = Vec::new();
-    let mut folios: Vec<Folio> = Vec::new();
+    let mut skeleton = PlantSkeleton::default();
+    // Bracket scope of each node, to tell lateral branches from continuations
+    let mut node_scope: Vec<usize> = Vec::new();
     let mut current_folio: Option<Vec<Vec3>> = None;
 
-    let turn_rad = turn_angle_deg.to_radians();
+    // Seeded so jitter is stable from frame to frame
+    let mut rng = StdRng::seed_from_u64(plant.seed);
+    let variation = &plant.variation;
+    let turn = |axis: &AxisAngle, symbol: char, rng: &mut StdRng| {
+        let mut angle = axis.angle.unwrap_or(lsystem.angle);
+        if axis.jitter > 0.0 {
+            angle += rng.random_range(-axis.jitter..=axis.jitter);
+        }
+        if let Some(v) = variation.get(symbol) {
+            angle += v.turn_angle.sample(rng);
+        }
+        angle.to_radians()
+    };
+    let step_factor = |symbol: char, rng: &mut StdRng| {
+        variation.get(symbol).map_or(1.0, |v| v.step_length.sample_factor(rng))
+    };
+    let mut leaf_scale = 1.0;
+
+    // Raw gradient input per node / folio, normalised once the maximum is known
+    let mut node_keys: Vec<f32> = Vec::new();
+    let mut folio_keys: Vec<f32> = Vec::new();
+    let mut folio_key = 0.0;
+
+    let chars: Vec<char> = lsystem_string.chars().collect();
+    // End of the query module being skipped; its parameters aren't turtle commands
+    let mut skip_to = 0;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if i < skip_to {
+            continue;
+        }
+        if let Some(module) = query_module_at(&chars, i) {
+            skeleton.queries.push(QueryPoint { symbol: i, position: turtle.pos, frame: turtle.rot });
+            skip_to = module.end;
+            continue;
+        }
+
+        let key = match plant.color_mode {
+            ColorMode::Index => 0.0,
+            ColorMode::Depth => stack.len() as f32,
+            ColorMode::Iteration => birth.get(i).copied().unwrap_or(0) as f32,
+            ColorMode::Distance => turtle.distance,
+        };
 
-    for c in lsystem_string.chars() {
         match c {
 
 
             '{' => {
                 current_folio = Some(vec![turtle.pos]);
+                folio_key = key;
+                leaf_scale = variation.get(c).map_or(1.0, |v| v.leaf_scale.sample_factor(&mut rng));
             }
             '}' => {
-                if let Some(mut verts) = current_folio.take() {
+                if let Some(verts) = current_folio.take() {
                     // Close the shape by connecting to the first vertex if needed
                     if verts.len() >= 3 {
-                        folios.push(Folio { vertices: verts, color: palette[turtle.color_index % palette.len()] });
+                        skeleton.folios.push(Folio { vertices: verts, color: palette.index(turtle.color_index), kind: turtle.surface, parent: turtle.last_node });
+                        folio_keys.push(folio_key);
                     }
                 }
             }
             'F' => {
                 // Move forward along local Y
-                let new_pos = turtle.pos + turtle.rot * Vec3::Y * step_size;
-                let color = palette[turtle.color_index % palette.len()];
-                segments.push(Segment { start: turtle.pos, end: new_pos, thickness: turtle.thickness, color: color});
+                let new_pos = turtle.pos + turtle.rot * Vec3::Y * step_size * step_factor(c, &mut rng);
+                let color = palette.index(turtle.color_index);
+                let parent = turtle.last_node;
+                let order = parent.map_or(0, |p| {
+                    skeleton.nodes[p].order + u32::from(node_scope[p] != turtle.branch)
+                });
+                turtle.last_node = Some(skeleton.push(SkeletonNode {
+                    start: turtle.pos,
+                    position: new_pos,
+                    frame: turtle.rot,
+                    radius: turtle.thickness * 0.5,
+                    color,
+                    depth: stack.len() as u32,
+                    order,
+                    parent,
+                    children: Vec::new(),
+                    symbol: i,
+                    distance: turtle.distance,
+                }));
+                node_scope.push(turtle.branch);
+                node_keys.push(key);
+                turtle.distance += turtle.pos.distance(new_pos);
                 turtle.pos = new_pos;
+
+                // Tropism: bend the heading towards the tropism vector by e * |H x T|
+                let bend_axis = (turtle.rot * Vec3::Y).cross(lsystem.tropism);
+                let bend = lsystem.tropism_strength * bend_axis.length();
+                if bend > 0.0 {
+                    turtle.rot = Quat::from_axis_angle(bend_axis.normalize(), bend) * turtle.rot;
+                }
             }
             'f' => {
                 // forward but without drawing a segment
-                turtle.pos += turtle.rot * Vec3::Y * (step_size/2.0);
+                let mut step = step_size / 2.0 * step_factor(c, &mut rng);
                 if let Some(ref mut verts) = current_folio {
+                    step *= leaf_scale;
+                    turtle.pos += turtle.rot * Vec3::Y * step;
                     verts.push(turtle.pos);
+                } else {
+                    turtle.pos += turtle.rot * Vec3::Y * step;
                 }
             }
-            '!' => turtle.thickness *= 0.9,
+            '!' => {
+                let decay = variation.get(c).map_or(1.0, |v| v.thickness_decay.sample_factor(&mut rng));
+                turtle.thickness *= (lsystem.thickness_decay * decay).min(1.0);
+            }
             '\'' => turtle.color_index += 1,
-            '+' => turtle.rot *= Quat::from_rotation_z(-turn_rad), // roll clockwise
-            '-' => turtle.rot *= Quat::from_rotation_z(turn_rad),  // roll counter-clockwise
-            '&' => turtle.rot *= Quat::from_rotation_x(turn_rad),  // pitch down
-            '^' => turtle.rot *= Quat::from_rotation_x(-turn_rad), // pitch up
-            '\\' => turtle.rot *= Quat::from_rotation_y(turn_rad), // yaw left
-            '/' => turtle.rot *= Quat::from_rotation_y(-turn_rad), // yaw right
-            '[' => stack.push(turtle),
-            ']' => turtle = stack.pop().unwrap(),
+            '%' => turtle.surface = SurfaceKind::Petal,
+            '+' => turtle.rot *= Quat::from_rotation_z(-turn(&lsystem.roll, c, &mut rng)), // roll clockwise
+            '-' => turtle.rot *= Quat::from_rotation_z(turn(&lsystem.roll, c, &mut rng)),  // roll counter-clockwise
+            '&' => turtle.rot *= Quat::from_rotation_x(turn(&lsystem.pitch, c, &mut rng)),  // pitch down
+            '^' => turtle.rot *= Quat::from_rotation_x(-turn(&lsystem.pitch, c, &mut rng)), // pitch up
+            '\\' => turtle.rot *= Quat::from_rotation_y(turn(&lsystem.yaw, c, &mut rng)), // yaw left
+            '/' => turtle.rot *= Quat::from_rotation_y(-turn(&lsystem.yaw, c, &mut rng)), // yaw right
+            '|' => turtle.rot *= Quat::from_rotation_z(PI), // turn around
+            '[' => {
+                stack.push(turtle);
+                branches += 1;
+                turtle.branch = branches;
+            }
+            // A stray `]` (easily typed in the rule editor) is skipped
+            ']' => {
+                if let Some(popped) = stack.pop() {
+                    turtle = popped;
+                }
+            }
             _ => {}
         }
     }
 
-    (segments, folios)
+    if plant.color_mode != ColorMode::Index {
+        let max_key = node_keys.iter().chain(&folio_keys).copied().fold(0.0, f32::max).max(f32::EPSILON);
+        for (node, key) in skeleton.nodes.iter_mut().zip(&node_keys) {
+            node.color = palette.sample(key / max_key);
+        }
+        for (folio, key) in skeleton.folios.iter_mut().zip(&folio_keys) {
+            folio.color = palette.sample(key / max_key);
+        }
+    }
+
+    if let ThicknessModel::Pipe { exponent, tip_radius } = plant.thickness {
+        apply_pipe_model(&mut skeleton, exponent, tip_radius);
+    }
+
+    skeleton
 }
 
-fn build_segment_mesh(segments: &[Segment], folios: &[Folio]) -> Mesh {
+/// Branch tubes with `sides` faces; UV U runs around the circumference and V along arc length times `texel_density`
+/// `sway` and `rig`, when given, must have been built from this same skeleton
+pub(crate) fn build_segment_mesh(skeleton: &PlantSkeleton, sway: &[Sway], texel_density: f32, sides: u32, rig: Option<&Rig>) -> Mesh {
     let mut positions = Vec::new();
     let mut normals = Vec::new();
+    let mut uvs = Vec::new();
     let mut indices = Vec::new();
     let mut colors = Vec::new();
+    let mut branch = BranchAttributes::default();
+    let mut skin = SkinAttributes::new(rig);
+    let sides = sides.max(3);
 
-    for seg in segments {
-        let dir = seg.end - seg.start;
+    for (i, (seg, sway)) in skeleton.nodes.iter().zip(sway).enumerate() {
+        let dir = seg.position - seg.start;
         let length = dir.length();
         if length == 0.0 { continue; }
 
         let rotation = Quat::from_rotation_arc(Vec3::Y, dir.normalize());
-        let translation = seg.start + dir * 0.5;
-        let scale = Vec3::new(seg.thickness, length, seg.thickness);
-        let transform = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+        let radius = seg.radius;
         let color = seg.color.to_srgba().to_f32_array();
 
-        let mut vertex_offset = 0;
-
-        // Define vertices for a unit rectangular prism along Y from y=-0.5..0.5
-        // (you can skew or taper these later if you want more organic shapes)
-        let verts = [
-            Vec3::new(-0.5, -0.5, -0.5),
-            Vec3::new( 0.5, -0.5, -0.5),
-            Vec3::new( 0.5,  0.5, -0.5),
-            Vec3::new(-0.5,  0.5, -0.5),
-            Vec3::new(-0.5, -0.5,  0.5),
-            Vec3::new( 0.5, -0.5,  0.5),
-            Vec3::new( 0.5,  0.5,  0.5),
-            Vec3::new(-0.5,  0.5,  0.5),
-        ];
-
-        // 12 triangles (6 faces × 2 triangles)
-        let face_indices = [
-            (0, 1, 2, 3), // back
-            (5, 4, 7, 6), // front
-            (4, 0, 3, 7), // left
-            (1, 5, 6, 2), // right
-            (3, 2, 6, 7), // top
-            (4, 5, 1, 0), // bottom
-        ];
+        // Two rings of sides + 1 vertices; the seam vertex is duplicated so U runs 0..1
+        let start_idx = positions.len() as u32;
+        for (center, along, at_end) in [(seg.start, seg.distance, false), (seg.position, seg.distance + length, true)] {
+            for k in 0..=sides {
+                let angle = k as f32 / sides as f32 * TAU;
+                let outward = rotation * Vec3::new(angle.cos(), 0.0, angle.sin());
+                let v = center + outward * radius;
+                positions.push([v.x, v.y, v.z]);
+                normals.push([outward.x, outward.y, outward.z]);
+                uvs.push([k as f32 / sides as f32, along * texel_density]);
+                colors.push(color);
+                branch.push(sway, along - seg.distance + sway.along);
+                skin.push(|rig| rig.segment_skin(i, at_end));
+            }
+        }
 
-        let face_normals = [
-            Vec3::NEG_Z, Vec3::Z, Vec3::NEG_X, Vec3::X, Vec3::Y, Vec3::NEG_Y,
-        ];
+        let ring_len = sides + 1;
+        for k in 0..sides {
+            let bottom = start_idx + k;
+            let top = bottom + ring_len;
+            indices.extend_from_slice(&[
+                bottom, top, bottom + 1,
+                bottom + 1, top, top + 1,
+            ]);
+        }
 
-        for (face_i, &(a, b, c, d)) in face_indices.iter().enumerate() {
-            let normal = rotation * face_normals[face_i];
-            let start_idx = positions.len() as u32;
+        // Flat end caps as fans around the centre
+        for (center, normal, flip, along) in [(seg.start, -dir / length, true, sway.along), (seg.position, dir / length, false, sway.along + length)] {
+            let center_idx = positions.len() as u32;
+            positions.push([center.x, center.y, center.z]);
+            normals.push([normal.x, normal.y, normal.z]);
+            uvs.push([0.5, 0.5]);
+            colors.push(color);
+            branch.push(sway, along);
+            skin.push(|rig| rig.segment_skin(i, !flip));
 
-            for &vi in &[a, b, c, d] {
-                let v = transform * verts[vi].extend(1.0);
+            for k in 0..sides {
+                let angle = k as f32 / sides as f32 * TAU;
+                let v = center + rotation * Vec3::new(angle.cos(), 0.0, angle.sin()) * radius;
                 positions.push([v.x, v.y, v.z]);
                 normals.push([normal.x, normal.y, normal.z]);
+                uvs.push([0.5 + angle.cos() * 0.5, 0.5 + angle.sin() * 0.5]);
                 colors.push(color);
+                branch.push(sway, along);
+                skin.push(|rig| rig.segment_skin(i, !flip));
             }
+            for k in 0..sides {
+                let a = center_idx + 1 + k;
+                let b = center_idx + 1 + (k + 1) % sides;
+                if flip {
+                    indices.extend_from_slice(&[center_idx, a, b]);
+                } else {
+                    indices.extend_from_slice(&[center_idx, b, a]);
+                }
+            }
+        }
+    }
 
-            // Two triangles per face
-            indices.extend_from_slice(&[
-                start_idx, start_idx + 1, start_idx + 2,
-                start_idx, start_idx + 2, start_idx + 3,
-            ]);
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    branch.insert_into(&mut mesh);
+    skin.insert_into(&mut mesh);
+    mesh.insert_indices(Indices::U32(indices));
+
+    mesh
+}
+
+/// Joint indices and weights, only written when the plant is rigged
+struct SkinAttributes<'a> {
+    rig: Option<&'a Rig>,
+    indices: Vec<[u16; 4]>,
+    weights: Vec<[f32; 4]>,
+}
+
+impl<'a> SkinAttributes<'a> {
+    fn new(rig: Option<&'a Rig>) -> Self {
+        Self { rig, indices: Vec::new(), weights: Vec::new() }
+    }
+
+    fn push(&mut self, skin: impl FnOnce(&Rig) -> ([u16; 4], [f32; 4])) {
+        if let Some(rig) = self.rig {
+            let (indices, weights) = skin(rig);
+            self.indices.push(indices);
+            self.weights.push(weights);
         }
+    }
 
-        vertex_offset += (verts.len() * 6) as u32; // optional tracking if you mix types
+    fn insert_into(self, mesh: &mut Mesh) {
+        if self.rig.is_some() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_INDEX, VertexAttributeValues::Uint16x4(self.indices));
+            mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, self.weights);
+        }
     }
+}
 
-    // leaves (flat double-sided quads/polygons)
-    for folio in folios {
+/// Per-vertex wind hierarchy data, see `crate::wind`
+#[derive(Default)]
+struct BranchAttributes {
+    depth: Vec<f32>,
+    distance: Vec<f32>,
+    pivot: Vec<[f32; 3]>,
+    phase: Vec<f32>,
+}
+
+impl BranchAttributes {
+    fn push(&mut self, sway: &Sway, distance: f32) {
+        self.depth.push(sway.depth);
+        self.distance.push(distance);
+        self.pivot.push(sway.pivot.to_array());
+        self.phase.push(sway.phase);
+    }
+
+    fn insert_into(self, mesh: &mut Mesh) {
+        mesh.insert_attribute(ATTRIBUTE_BRANCH_DEPTH, self.depth);
+        mesh.insert_attribute(ATTRIBUTE_BRANCH_DISTANCE, self.distance);
+        mesh.insert_attribute(ATTRIBUTE_BRANCH_PIVOT, self.pivot);
+        mesh.insert_attribute(ATTRIBUTE_BRANCH_PHASE, self.phase);
+    }
+}
+
+/// The skeleton's polygons of one `kind`; back faces come from the material (`double_sided`),
+/// `sway` is the branch data of the nodes they hang from
+pub(crate) fn build_folio_mesh(skeleton: &PlantSkeleton, kind: SurfaceKind, sway: &[Sway], rig: Option<&Rig>) -> Mesh {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    let mut colors = Vec::new();
+    let mut branch = BranchAttributes::default();
+    let mut skin = SkinAttributes::new(rig);
+
+    for folio in skeleton.folios.iter().filter(|f| f.kind == kind) {
         if folio.vertices.len() < 3 {
             continue;
         }
@@ -174,69 +386,221 @@ fn build_segment_mesh(segments: &[Segment], folios: &[Folio]) -> Mesh {
             v1.cross(v2).normalize()
         };
 
+        // Planar UVs in the leaf's own frame: V from the base to the farthest vertex, U across it
+        let origin = folio.vertices[0];
+        let axis_v = folio.vertices.iter()
+            .map(|v| *v - origin)
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap()
+            .normalize_or(Vec3::Y);
+        let axis_u = normal.cross(axis_v).normalize_or(Vec3::X);
+        let projected: Vec<Vec2> = folio.vertices.iter()
+            .map(|v| Vec2::new((*v - origin).dot(axis_u), (*v - origin).dot(axis_v)))
+            .collect();
+        let min = projected.iter().copied().fold(Vec2::MAX, Vec2::min);
+        let max = projected.iter().copied().fold(Vec2::MIN, Vec2::max);
+        let extent = (max - min).max(Vec2::splat(f32::EPSILON));
+
+        // Each leaf is its own branch, pivoting where it attaches
+        let parent = folio.parent.and_then(|p| sway.get(p));
+        let leaf = Sway {
+            depth: parent.map_or(0.0, |p| p.depth + 1.0),
+            pivot: origin,
+            along: 0.0,
+            phase: parent.map_or(0.0, |p| p.phase) + base_index as f32 * 0.37,
+        };
+
         // Push vertices
-        for v in &folio.vertices {
+        for (v, p) in folio.vertices.iter().zip(&projected) {
             positions.push([v.x, v.y, v.z]);
             normals.push([normal.x, normal.y, normal.z]);
+            uvs.push(((*p - min) / extent).to_array());
             colors.push(color);
+            branch.push(&leaf, v.distance(origin));
+            skin.push(|rig| rig.attached_skin(folio.parent));
         }
 
         // Triangulate as a fan
         for i in 1..folio.vertices.len() as u32 - 1 {
             indices.extend_from_slice(&[base_index, base_index + i, base_index + i + 1]);
         }
-
-        // Double-sided: duplicate triangles with flipped normal
-        let back_base = positions.len() as u32;
-        for v in &folio.vertices {
-            positions.push([v.x, v.y, v.z]);
-            normals.push([-normal.x, -normal.y, -normal.z]);
-            colors.push(color);
-        }
-        for i in 1..folio.vertices.len() as u32 - 1 {
-            indices.extend_from_slice(&[back_base, back_base + i + 1, back_base + i]);
-        }
     }
 
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD);
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
     mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    branch.insert_into(&mut mesh);
+    skin.insert_into(&mut mesh);
     mesh.insert_indices(Indices::U32(indices));
 
     mesh
 }
 
+type DrawnPlant<'a> = (
+    Entity,
+    &'a Plant,
+    &'a mut PlantStats,
+    &'a mut ShadowCasters,
+    Option<&'a ImpostorCache>,
+    Option<&'a ImpostorBake>,
+);
+
 pub fn draw_plant(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    plants: Query<(Entity, &Plant)>,
+    mut wind_materials: ResMut<Assets<WindMaterial>>,
+    mut bindposes: ResMut<Assets<SkinnedMeshInverseBindposes>>,
+    wind: Res<Wind>,
+    volumes: Res<VolumeMeshes>,
+    mut plants: Query<DrawnPlant, Changed<Plant>>,
 ) {
-    for (entity, plant) in &plants {
+    for (entity, plant, mut stats, mut casters, cache, bake) in &mut plants {
         commands.entity(entity).despawn_children();
 
-        let (segments, folios) = interpret_plant(&plant.current_string, plant.step_size, plant.lsystem.angle, plant.root_thickness, &plant.palette);
-
-        let mesh = build_segment_mesh(&segments, &folios);
-        let mesh_handle = meshes.add(mesh);
-
-        commands.spawn((
-            Mesh3d(mesh_handle),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb(0.2, 0.6, 0.25),
-                perceptual_roughness: 0.8, // very diffuse
-                metallic: 0.0,             // plants aren't metallic
-                reflectance: 0.2,          // subtle highlights
-                ..default()
-                //base_color: Color::srgb(0.0, 1.0, 0.0),
-                //cull_mode: None,
-            })),
-            Transform::default(),
-            GlobalTransform::default(),
-            Visibility::default(),
-            Name::new("PlantMesh"),
-            ChildOf(entity),
-        ));
+        let start = Instant::now();
+        let skeleton = plant.skeleton(volumes.get(plant, &meshes));
+        stats.interpret_time = start.elapsed();
+        stats.record_string(plant);
+        stats.record_skeleton(&skeleton);
+        *casters = ShadowCasters::from_skeleton(&skeleton);
+        let start = Instant::now();
+        stats.vertices = 0;
+        stats.triangles = 0;
+        let lod = &plant.lod;
+
+        // Shared by every level so only three materials exist per plant
+        let height = skeleton.height();
+        let mut swaying = |surface: &crate::material::SurfaceMaterial, flutter: bool| {
+            wind_materials.add(WindMaterial {
+                base: surface.to_standard_material(),
+                extension: WindExtension::new(&wind, flutter, height),
+            })
+        };
+        let bark = swaying(&plant.materials.bark, false);
+        let leaf = swaying(&plant.materials.leaf, true);
+        let petal = swaying(&plant.materials.petal, true);
+        let texel_density = plant.materials.texel_density;
+
+        // Only the full level is skinned; distant levels stay in the rest pose
+        let rig = (plant.rig.enabled && !skeleton.nodes.is_empty()).then(|| Rig::new(&skeleton, &plant.rig));
+        let skinned = rig.as_ref().map(|rig| rig.spawn(&mut commands, &mut bindposes, entity));
+
+        let mut levels = Vec::new();
+        if lod.enabled {
+            let merged = merge_segments(&skeleton, lod.merge_length);
+            let culled = cull_thin_segments(&merged, lod.cull_thickness);
+            levels.push((skeleton, lod.full_sides));
+            levels.push((merged, lod.low_sides));
+            levels.push((culled, lod.low_sides));
+        } else {
+            levels.push((skeleton, lod.full_sides));
+        }
+
+        // Tangents let normal-mapped bark and leaf materials light correctly
+        let with_tangents = |name: &str, mut mesh: Mesh| {
+            if mesh.count_vertices() == 0 {
+                return None;
+            }
+            if let Err(e) = mesh.generate_tangents() {
+                warn!("could not generate tangents for {name}: {e}");
+            }
+            Some(mesh)
+        };
+
+        // Every level keeps all the leaves and petals, so they are built once from the full
+        // skeleton and shared; only a skinned full level needs its own
+        let folio_parts = [
+            ("Leaves", SurfaceKind::Leaf, &leaf, &plant.materials.leaf),
+            ("Petals", SurfaceKind::Petal, &petal, &plant.materials.petal),
+        ];
+        let mut shared_folios = Vec::new();
+        if lod.enabled || rig.is_none() {
+            let sway = branch_sway(&levels[0].0);
+            for (name, kind, material, surface) in folio_parts {
+                if let Some(mesh) = with_tangents(name, build_folio_mesh(&levels[0].0, kind, &sway, None)) {
+                    shared_folios.push((name, meshes.add(mesh.clone()), mesh, material, surface));
+                }
+            }
+        }
+
+        // The coarsest level is what gets baked into the impostor
+        let mut far_parts = Vec::new();
+
+        for (level, (level_skeleton, sides)) in levels.iter().enumerate() {
+            let level_rig = if level == 0 { rig.as_ref() } else { None };
+            let sway = branch_sway(level_skeleton);
+            let mut built = vec![
+                ("Bark", build_segment_mesh(level_skeleton, &sway, texel_density, *sides, level_rig), &bark, &plant.materials.bark),
+            ];
+            if level_rig.is_some() {
+                for (name, kind, material, surface) in folio_parts {
+                    built.push((name, build_folio_mesh(level_skeleton, kind, &sway, level_rig), material, surface));
+                }
+            }
+
+            let mut count = |mesh: &Mesh, surface: &crate::material::SurfaceMaterial| {
+                if level == 0 {
+                    stats.vertices += mesh.count_vertices();
+                    stats.triangles += mesh.indices().map_or(0, |i| i.len() / 3);
+                }
+                if lod.enabled && level == levels.len() - 1 {
+                    far_parts.push((mesh.clone(), surface.base_color));
+                }
+            };
+            let mut parts = Vec::new();
+            for (name, mesh, material, surface) in built {
+                if let Some(mesh) = with_tangents(name, mesh) {
+                    count(&mesh, surface);
+                    parts.push((name, meshes.add(mesh), material));
+                }
+            }
+            if level_rig.is_none() {
+                for (name, handle, mesh, material, surface) in &shared_folios {
+                    count(mesh, surface);
+                    parts.push((name, handle.clone(), *material));
+                }
+            }
+
+            // One child per material so each sub-mesh gets its own PBR settings
+            for (name, mesh, material) in parts {
+                let mut child = commands.spawn((
+                    Mesh3d(mesh),
+                    MeshMaterial3d(material.clone()),
+                    Transform::default(),
+                    GlobalTransform::default(),
+                    Visibility::default(),
+                    Name::new(format!("PlantMesh{name}Lod{level}")),
+                    ChildOf(entity),
+                ));
+                if lod.enabled {
+                    child.insert(lod.range(level));
+                }
+                if let Some(skin) = skinned.as_ref().filter(|_| level == 0) {
+                    child.insert(skin.clone());
+                }
+            }
+        }
+
+        // The far level is baked into an impostor off the main thread, and only once the
+        // plant has finished growing: until then it keeps showing the impostor it has
+        if far_parts.is_empty() {
+            commands.entity(entity).remove::<(ImpostorCache, ImpostorBake)>();
+        } else {
+            let far_refs: Vec<(&Mesh, Color)> = far_parts.iter().map(|(m, c)| (m, *c)).collect();
+            let key = impostor_key(&far_refs, lod.impostor_views, lod.impostor_resolution);
+            let stale = cache.is_none_or(|cache| cache.key != key);
+            let baking = bake.is_some_and(|bake| bake.key == key);
+            let first = cache.is_none() && bake.is_none();
+            if stale && !baking && (plant.finished() || first) {
+                let bake = ImpostorBake::start(key, far_parts, lod.impostor_views, lod.impostor_resolution);
+                commands.entity(entity).insert(bake);
+            }
+            if let Some(cache) = cache {
+                spawn_billboard(&mut commands, entity, cache, lod.range(3));
+            }
+        }
+        stats.mesh_time = start.elapsed();
     }
 }
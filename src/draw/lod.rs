@@ -0,0 +1,116 @@
+use bevy::{camera::visibility::VisibilityRange, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::skeleton::PlantSkeleton;
+use crate::impostor::Impostor;
+
+/// Distance-switched detail levels for one plant
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LodSettings {
+    pub enabled: bool,
+    /// Camera distances at which full → reduced → culled → billboard switch
+    pub distances: [f32; 3],
+    /// Tube sides at full detail
+    pub full_sides: u32,
+    /// Tube sides for the reduced levels
+    pub low_sides: u32,
+    /// Single-child chains are merged until a merged segment reaches this length
+    pub merge_length: f32,
+    /// Branches thinner than this are dropped from the culled level
+    pub cull_thickness: f32,
+    /// Azimuth angles baked into the far impostor
+    #[serde(default = "default_impostor_views")]
+    pub impostor_views: u32,
+    /// Pixel size of each impostor view
+    #[serde(default = "default_impostor_resolution")]
+    pub impostor_resolution: u32,
+}
+
+fn default_impostor_views() -> u32 {
+    8
+}
+
+fn default_impostor_resolution() -> u32 {
+    128
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distances: [400.0, 800.0, 1600.0],
+            full_sides: 8,
+            low_sides: 4,
+            merge_length: 20.0,
+            cull_thickness: 0.5,
+            impostor_views: default_impostor_views(),
+            impostor_resolution: default_impostor_resolution(),
+        }
+    }
+}
+
+impl LodSettings {
+    /// Visibility range of level `level` (0..=3), crossfading over the last 10% before each switch
+    pub fn range(&self, level: usize) -> VisibilityRange {
+        let margin = |d: f32| (d * 0.9)..d;
+        let start = if level == 0 { 0.0..0.0 } else { margin(self.distances[level - 1]) };
+        let end = if level < self.distances.len() { margin(self.distances[level]) } else { f32::MAX..f32::MAX };
+        VisibilityRange { start_margin: start, end_margin: end, use_aabb: false }
+    }
+}
+
+/// Merge chains of segments that have a single child, until each reaches `merge_length`
+pub fn merge_segments(skeleton: &PlantSkeleton, merge_length: f32) -> PlantSkeleton {
+    skeleton.filtered(|node, target| {
+        let only_child = node.parent.is_some_and(|p| skeleton.nodes[p].children.len() == 1);
+        match target {
+            Some(target) if only_child && target.length() < merge_length => {
+                target.position = node.position;
+                false
+            }
+            _ => true,
+        }
+    })
+}
+
+/// Drop segments thinner than `min_thickness`; their children and leaves move to the nearest survivor
+pub fn cull_thin_segments(skeleton: &PlantSkeleton, min_thickness: f32) -> PlantSkeleton {
+    skeleton.filtered(|node, _| node.radius * 2.0 >= min_thickness)
+}
+
+/// Far-distance stand-in that turns about its local Y axis to face the camera
+#[derive(Component)]
+pub struct Billboard;
+
+/// Impostor cell selection and the material it drives
+type ImpostorView<'a> = (&'a mut Impostor, &'a MeshMaterial3d<StandardMaterial>);
+
+/// Yaw billboards toward the camera and show the impostor view baked nearest that angle
+pub fn billboard_face_camera(
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    parents: Query<&GlobalTransform, Without<Billboard>>,
+    mut billboards: Query<(&mut Transform, &ChildOf, Option<ImpostorView<'static>>), With<Billboard>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(camera) = cameras.iter().next() else { return };
+
+    for (mut transform, child_of, impostor) in &mut billboards {
+        let Ok(parent) = parents.get(child_of.parent()) else { continue };
+
+        // Work in the plant's local space so a tilted plant still yaws about its own axis
+        let local_camera = parent.affine().inverse().transform_point3(camera.translation());
+        let yaw = local_camera.x.atan2(local_camera.z);
+        transform.rotation = Quat::from_rotation_y(yaw);
+
+        // Only touch the material when the view changes, so it isn't re-uploaded every frame
+        if let Some((mut impostor, material)) = impostor {
+            let view = impostor.view_for_yaw(yaw);
+            if impostor.current != Some(view)
+                && let Some(material) = materials.get_mut(material)
+            {
+                material.uv_transform = impostor.uv_transform(view);
+                impostor.current = Some(view);
+            }
+        }
+    }
+}
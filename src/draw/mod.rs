@@ -1,3 +1,7 @@
 pub mod draw;
+pub mod thickness;
+pub mod lod;
 
 pub use draw::draw_plant;
+pub use thickness::ThicknessModel;
+pub use lod::{billboard_face_camera, LodSettings};
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use crate::skeleton::PlantSkeleton;
+
+/// How branch thickness is assigned
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ThicknessModel {
+    /// Start at `root_thickness` and shrink by the L-system's decay factor at every `!`
+    #[default]
+    Decay,
+    /// Pipe model / da Vinci rule, `r_parent^n = Σ r_child^n`, worked out from the tips down
+    Pipe { exponent: f32, tip_radius: f32 },
+}
+
+/// Replace node radii with pipe-model radii derived from the branching topology
+pub fn apply_pipe_model(skeleton: &mut PlantSkeleton, exponent: f32, tip_radius: f32) {
+    let exponent = exponent.max(0.01);
+
+    // Parents always come before their children, so a reverse pass sees every child first
+    for i in (0..skeleton.nodes.len()).rev() {
+        let sum: f32 = skeleton.nodes[i].children.iter()
+            .map(|&child| skeleton.nodes[child].radius.powf(exponent))
+            .sum();
+        skeleton.nodes[i].radius = if sum > 0.0 { sum.powf(1.0 / exponent) } else { tip_radius };
+    }
+}
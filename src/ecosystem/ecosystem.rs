@@ -0,0 +1,393 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::field::{fbm, GrayImage};
+use crate::light::LightGrid;
+use crate::plant::{GrowthTimer, SelectedPlant};
+use crate::presets::load_preset;
+use crate::simulation::{LifeCycle, Simulation};
+use crate::terrain::{Terrain, TerrainMesh, TerrainSettings};
+
+/// Where plants may grow, 0..1 over the region; thins out the Poisson-disk samples
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum DensityMap {
+    Uniform,
+    /// Fractal noise patches; `frequency` is patches per 1000 units
+    Noise { frequency: f32, octaves: u32 },
+    /// Grayscale image stretched over the region, white = full density
+    Image { path: String },
+}
+
+/// One species in the mix, by preset name
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeciesWeight {
+    pub preset: String,
+    pub weight: f32,
+    #[serde(default)]
+    pub mask: SpeciesMask,
+    /// Flowering, seed dispersal and lifespan in the simulation
+    #[serde(default)]
+    pub life: LifeCycle,
+}
+
+/// Where on the terrain a species may grow
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeciesMask {
+    /// Allowed altitude range, 0 at the lowest point of the terrain and 1 at the highest
+    pub altitude: Vec2,
+    /// Steepest ground in degrees
+    pub max_slope: f32,
+}
+
+impl Default for SpeciesMask {
+    fn default() -> Self {
+        Self { altitude: Vec2::new(0.0, 1.0), max_slope: 90.0 }
+    }
+}
+
+impl SpeciesMask {
+    pub fn allows(&self, altitude: f32, slope: f32) -> bool {
+        (self.altitude.x..=self.altitude.y).contains(&altitude) && slope <= self.max_slope
+    }
+}
+
+/// How the ecosystem is scattered; everything derives from `world_seed`
+#[derive(Resource, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EcosystemSettings {
+    pub world_seed: u64,
+    /// XZ rectangle plants are placed in
+    pub region_min: Vec2,
+    pub region_max: Vec2,
+    #[serde(default)]
+    pub terrain: TerrainSettings,
+    /// Tilt plants to the ground's normal instead of growing straight up
+    #[serde(default)]
+    pub align_to_normal: bool,
+    /// Poisson-disk radius: no two plants are closer than this
+    pub min_spacing: f32,
+    pub density: DensityMap,
+    pub species: Vec<SpeciesWeight>,
+    /// Uniform scale range per instance
+    pub scale: Vec2,
+    /// Random rotation about the up axis
+    pub random_yaw: bool,
+    /// Growth starts are spread over this many seconds
+    pub max_start_delay: f32,
+    /// Regenerate on the next frame
+    #[serde(skip)]
+    pub respawn: bool,
+}
+
+impl Default for EcosystemSettings {
+    fn default() -> Self {
+        Self {
+            world_seed: 1,
+            region_min: Vec2::splat(-300.0),
+            region_max: Vec2::splat(300.0),
+            terrain: TerrainSettings::default(),
+            align_to_normal: false,
+            min_spacing: 120.0,
+            density: DensityMap::Uniform,
+            species: vec![
+                SpeciesWeight { preset: "Flower".to_string(), weight: 3.0, mask: SpeciesMask::default(), life: LifeCycle::default() },
+                // Ferns keep to the shady, lower ground and spread from their growing tips
+                SpeciesWeight {
+                    preset: "Fern".to_string(),
+                    weight: 1.0,
+                    mask: SpeciesMask { altitude: Vec2::new(0.0, 0.5), max_slope: 30.0 },
+                    life: LifeCycle { flower: 'X', seeds_per_flower: 0.002, lifespan: 20, ..default() },
+                },
+            ],
+            scale: Vec2::new(0.8, 1.2),
+            random_yaw: true,
+            max_start_delay: 3.0,
+            respawn: false,
+        }
+    }
+}
+
+/// A plant placed by the scatter
+pub struct Instance {
+    pub species: String,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+    pub seed: u64,
+    pub start_delay: f32,
+}
+
+impl Instance {
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.position)
+            .with_rotation(self.rotation)
+            .with_scale(Vec3::splat(self.scale))
+    }
+}
+
+/// Marks plants spawned by the ecosystem, so regenerating replaces only those
+#[derive(Component)]
+pub struct EcosystemMember;
+
+impl EcosystemSettings {
+    /// Place every plant on `terrain`. Positions and per-instance values use separate random
+    /// streams, so changing the species mix doesn't move the plants.
+    pub fn scatter(&self, terrain: &Terrain) -> Vec<Instance> {
+        if self.region_min.cmpgt(self.region_max).any() {
+            return Vec::new();
+        }
+        let density = self.density_sampler();
+        let mut placement = StdRng::seed_from_u64(self.world_seed);
+        let mut variation = StdRng::seed_from_u64(self.world_seed ^ 0x9E37_79B9_7F4A_7C15);
+
+        poisson_disk(self.region_min, self.region_max, self.min_spacing, &mut placement)
+            .into_iter()
+            .filter(|&p| placement.random::<f32>() < density(p))
+            .filter_map(|p| {
+                // Only species whose mask fits this spot compete for it
+                let (altitude, slope) = (terrain.altitude(p), terrain.slope(p));
+                let eligible: Vec<&SpeciesWeight> = self.species.iter().filter(|s| s.mask.allows(altitude, slope)).collect();
+                let total_weight: f32 = eligible.iter().map(|s| s.weight.max(0.0)).sum();
+                let mut roll = variation.random::<f32>() * total_weight;
+                let species = eligible.into_iter().find(|s| {
+                    roll -= s.weight.max(0.0);
+                    roll < 0.0
+                })?;
+                Some(self.instance_at(&species.preset, p, terrain, &mut variation))
+            })
+            .collect()
+    }
+
+    /// A plant of `species` standing at `p` on the terrain, with its rotation, scale and seed
+    /// drawn from `rng`
+    pub fn instance_at(&self, species: &str, p: Vec2, terrain: &Terrain, rng: &mut StdRng) -> Instance {
+        let (low, high) = (self.scale.x.min(self.scale.y), self.scale.x.max(self.scale.y));
+        let yaw = Quat::from_rotation_y(if self.random_yaw { rng.random_range(0.0..std::f32::consts::TAU) } else { 0.0 });
+        let tilt = if self.align_to_normal { Quat::from_rotation_arc(Vec3::Y, terrain.normal(p)) } else { Quat::IDENTITY };
+        Instance {
+            species: species.to_string(),
+            position: Vec3::new(p.x, terrain.height(p), p.y),
+            rotation: tilt * yaw,
+            scale: rng.random_range(low..=high),
+            seed: rng.random(),
+            start_delay: rng.random::<f32>() * self.max_start_delay,
+        }
+    }
+
+    /// The density map as a function of XZ position, with its image loaded once
+    pub fn density_sampler(&self) -> impl Fn(Vec2) -> f32 + '_ {
+        let image = match &self.density {
+            DensityMap::Image { path } => match GrayImage::load(Path::new(path)) {
+                Ok(image) => Some(image),
+                Err(e) => {
+                    warn!("could not load density map {path}: {e}");
+                    None
+                }
+            },
+            _ => None,
+        };
+        let size = (self.region_max - self.region_min).max(Vec2::splat(1.0));
+        move |p: Vec2| match &self.density {
+            DensityMap::Uniform => 1.0,
+            DensityMap::Noise { frequency, octaves } => fbm(p * frequency / 1000.0, *octaves, self.world_seed),
+            DensityMap::Image { .. } => image.as_ref().map_or(1.0, |image| image.sample((p - self.region_min) / size)),
+        }
+    }
+
+    /// Whether `p` lies in the planting region
+    pub fn contains(&self, p: Vec2) -> bool {
+        p.cmpge(self.region_min).all() && p.cmple(self.region_max).all()
+    }
+}
+
+/// Bridson's Poisson-disk sampling over a rectangle: points at least `radius` apart
+fn poisson_disk(min: Vec2, max: Vec2, radius: f32, rng: &mut StdRng) -> Vec<Vec2> {
+    const ATTEMPTS: usize = 30;
+    if min.cmpgt(max).any() {
+        return Vec::new();
+    }
+    let radius = radius.max(1.0);
+    let cell = radius / std::f32::consts::SQRT_2;
+    let dims = ((max - min) / cell).ceil().as_uvec2().max(UVec2::ONE);
+    let mut grid: Vec<Option<usize>> = vec![None; (dims.x * dims.y) as usize];
+    let cell_of = |p: Vec2| ((p - min) / cell).as_uvec2().min(dims - 1);
+    let index = |c: UVec2| (c.y * dims.x + c.x) as usize;
+
+    let first = Vec2::new(rng.random_range(min.x..=max.x), rng.random_range(min.y..=max.y));
+    let mut points = vec![first];
+    let mut active = vec![0];
+    grid[index(cell_of(first))] = Some(0);
+
+    while !active.is_empty() {
+        let slot = rng.random_range(0..active.len());
+        let origin = points[active[slot]];
+        let found = (0..ATTEMPTS).find_map(|_| {
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let distance = rng.random_range(radius..2.0 * radius);
+            let candidate = origin + Vec2::from_angle(angle) * distance;
+            if candidate.cmplt(min).any() || candidate.cmpgt(max).any() {
+                return None;
+            }
+            let c = cell_of(candidate).as_ivec2();
+            let clear = (-2..=2).all(|dy| (-2..=2).all(|dx| {
+                let n = c + IVec2::new(dx, dy);
+                if n.cmplt(IVec2::ZERO).any() || n.cmpge(dims.as_ivec2()).any() {
+                    return true;
+                }
+                grid[index(n.as_uvec2())]
+                    .is_none_or(|i| points[i].distance_squared(candidate) >= radius * radius)
+            }));
+            clear.then_some(candidate)
+        });
+        match found {
+            Some(p) => {
+                grid[index(cell_of(p))] = Some(points.len());
+                active.push(points.len());
+                points.push(p);
+            }
+            None => {
+                active.swap_remove(slot);
+            }
+        }
+    }
+    points
+}
+
+fn spawn_instances(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    settings: &EcosystemSettings,
+    selected: &mut SelectedPlant,
+) {
+    let terrain = Terrain::generate(&settings.terrain, settings.world_seed);
+    commands.spawn((
+        Mesh3d(meshes.add(terrain.mesh())),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgb(0.33, 0.40, 0.22),
+            perceptual_roughness: 0.95,
+            ..default()
+        })),
+        Transform::default(),
+        TerrainMesh,
+        Name::new("Terrain"),
+    ));
+
+    for instance in settings.scatter(&terrain) {
+        let Some(specimen) = load_preset(&instance.species) else {
+            warn!("unknown species {}", instance.species);
+            continue;
+        };
+        let entity = commands.spawn((
+            specimen.to_plant().with_seed(instance.seed),
+            GrowthTimer::delayed(instance.start_delay),
+            instance.transform(),
+            GlobalTransform::default(),
+            Visibility::default(),
+            EcosystemMember,
+            Name::new(instance.species),
+        )).id();
+        selected.0.get_or_insert(entity);
+    }
+    commands.insert_resource(terrain);
+}
+
+pub fn spawn_ecosystem(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<EcosystemSettings>,
+    mut selected: ResMut<SelectedPlant>,
+    mut grid: ResMut<LightGrid>,
+) {
+    grid.fit_ecosystem(&settings);
+    spawn_instances(&mut commands, &mut meshes, &mut materials, &settings, &mut selected);
+}
+
+type Spawned = Or<(With<EcosystemMember>, With<TerrainMesh>)>;
+
+/// Replace the ecosystem's plants and terrain when the UI asks for it
+pub fn respawn_ecosystem(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut settings: ResMut<EcosystemSettings>,
+    mut selected: ResMut<SelectedPlant>,
+    mut grid: ResMut<LightGrid>,
+    members: Query<Entity, Spawned>,
+) {
+    if !settings.respawn {
+        return;
+    }
+    settings.respawn = false;
+    for entity in &members {
+        commands.entity(entity).despawn();
+    }
+    selected.0 = None;
+    // A running simulation's plants are gone with the rest
+    commands.remove_resource::<Simulation>();
+    grid.fit_ecosystem(&settings);
+    spawn_instances(&mut commands, &mut meshes, &mut materials, &settings, &mut selected);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scatter_is_reproducible_and_spaced() {
+        let settings = EcosystemSettings {
+            density: DensityMap::Noise { frequency: 3.0, octaves: 2 },
+            ..default()
+        };
+        let terrain = Terrain::generate(&settings.terrain, settings.world_seed);
+        let a = settings.scatter(&terrain);
+        let b = settings.scatter(&terrain);
+
+        assert!(!a.is_empty());
+        assert!(a.iter().zip(&b).all(|(a, b)| a.position == b.position && a.seed == b.seed && a.species == b.species));
+        for (i, p) in a.iter().enumerate() {
+            assert_eq!(p.position.y, terrain.height(p.position.xz()));
+            for q in &a[i + 1..] {
+                assert!(p.position.distance(q.position) >= settings.min_spacing - 1e-3);
+            }
+        }
+
+        let reversed = EcosystemSettings { region_min: settings.region_max, region_max: settings.region_min, ..settings.clone() };
+        assert!(reversed.scatter(&terrain).is_empty());
+    }
+
+    #[test]
+    fn masks_keep_species_off_the_wrong_ground() {
+        let species = |preset: &str, mask| SpeciesWeight { preset: preset.to_string(), weight: 1.0, mask, life: default() };
+        let settings = EcosystemSettings {
+            region_min: Vec2::splat(-480.0),
+            region_max: Vec2::splat(480.0),
+            terrain: TerrainSettings { relief: 400.0, ..default() },
+            min_spacing: 25.0,
+            species: vec![
+                species("Flat", SpeciesMask { max_slope: 20.0, ..default() }),
+                species("Low", SpeciesMask { altitude: Vec2::new(0.0, 0.4), ..default() }),
+                species("Anywhere", SpeciesMask::default()),
+            ],
+            ..default()
+        };
+        let terrain = Terrain::generate(&settings.terrain, settings.world_seed);
+        let instances = settings.scatter(&terrain);
+        let ground = |name: &str| instances.iter()
+            .filter(|i| i.species == name)
+            .map(|i| (terrain.altitude(i.position.xz()), terrain.slope(i.position.xz())))
+            .collect::<Vec<_>>();
+
+        let (flat, low, anywhere) = (ground("Flat"), ground("Low"), ground("Anywhere"));
+        assert!(!flat.is_empty() && !low.is_empty());
+        assert!(flat.iter().all(|&(_, slope)| slope <= 20.0));
+        assert!(low.iter().all(|&(altitude, _)| altitude <= 0.4));
+        // The terrain has the ground they were kept off
+        assert!(anywhere.iter().any(|&(_, slope)| slope > 20.0));
+        assert!(anywhere.iter().any(|&(altitude, _)| altitude > 0.4));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod ecosystem;
+
+pub use ecosystem::{respawn_ecosystem, spawn_ecosystem, DensityMap, EcosystemMember, EcosystemSettings, Instance, SpeciesMask, SpeciesWeight};
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One communication module (`?E(…)`) sent to the environment, in plant space
+pub struct Request {
+    pub name: char,
+    pub position: Vec3,
+    /// Turtle orientation; local Y is the heading
+    pub frame: Quat,
+    /// The module's parameters; the environment overwrites them with its answer
+    pub params: Vec<f32>,
+}
+
+impl Request {
+    pub fn heading(&self) -> Vec3 {
+        self.frame * Vec3::Y
+    }
+}
+
+/// Surroundings an open L-system grows into (Měch & Prusinkiewicz 1996).
+/// Implement this to couple plants to anything in Rust and grow with `Plant::step_in`.
+pub trait Environment {
+    /// Answer every communication module of one plant at once, so environments can weigh
+    /// modules against each other
+    fn respond(&self, requests: &mut [Request]);
+}
+
+/// Built-in environments; each answers `?E(a)` by writing into `a`
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum EnvironmentKind {
+    /// Leave communication modules untouched
+    #[default]
+    None,
+    /// Ground plane: 1 when the turtle is below `height`, 0 above
+    Ground { height: f32 },
+    /// Topiary pruning: 1 when the turtle is outside the ellipsoid, 0 inside
+    Topiary { center: Vec3, radii: Vec3 },
+    /// Directional light field: light reaching the turtle, from `ambient` when facing away or
+    /// deep in the canopy to 1 when facing the light at the top. Light fades by `extinction`
+    /// per unit below `top` (Beer-Lambert).
+    Light { direction: Vec3, ambient: f32, extinction: f32, top: f32 },
+    /// Light competition: answered from the shared `LightGrid` by the step system (see
+    /// `LightGrid::view`); plain `Plant::step` leaves modules untouched
+    Competition,
+}
+
+impl EnvironmentKind {
+    fn answer(&self, request: &Request) -> Option<f32> {
+        let position = request.position;
+        match *self {
+            EnvironmentKind::None | EnvironmentKind::Competition => None,
+            EnvironmentKind::Ground { height } => Some(if position.y < height { 1.0 } else { 0.0 }),
+            EnvironmentKind::Topiary { center, radii } => {
+                let scaled = (position - center) / radii.max(Vec3::splat(f32::EPSILON));
+                Some(if scaled.length_squared() > 1.0 { 1.0 } else { 0.0 })
+            }
+            EnvironmentKind::Light { direction, ambient, extinction, top } => {
+                let facing = request.heading().dot(direction.normalize_or(Vec3::Y)).max(0.0);
+                let transmitted = (-extinction * (top - position.y).max(0.0)).exp();
+                Some(ambient + (1.0 - ambient) * facing * transmitted)
+            }
+        }
+    }
+}
+
+impl Environment for EnvironmentKind {
+    fn respond(&self, requests: &mut [Request]) {
+        for request in requests.iter_mut().filter(|r| r.name == 'E') {
+            if let Some(value) = self.answer(request) {
+                match request.params.first_mut() {
+                    Some(first) => *first = value,
+                    None => request.params.push(value),
+                }
+            }
+        }
+    }
+}
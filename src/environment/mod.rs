@@ -0,0 +1,3 @@
+pub mod environment;
+
+pub use environment::{Environment, EnvironmentKind, Request};
@@ -0,0 +1,237 @@
+use std::{fs, path::Path};
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::ecosystem::EcosystemMember;
+use crate::lsystem::MutationRates;
+use crate::plant::SelectedPlant;
+use crate::specimen::{Specimen, SpecimenError};
+use crate::terrain::TerrainMesh;
+
+/// One plant in the breeding lineage
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Genome {
+    pub id: u32,
+    /// None for the founder, one for a mutant, two for a cross
+    pub parents: Vec<u32>,
+    pub generation: u32,
+    /// What the breeding drew from; with the parents and rates it rebreeds this genome exactly
+    pub seed: u64,
+    pub rates: MutationRates,
+    pub specimen: Specimen,
+}
+
+impl Genome {
+    /// This genome's offspring: crossed with `mate` if there is one, then mutated. The same
+    /// parents, seed and rates always give the same child.
+    pub fn offspring(&self, mate: Option<&Genome>, id: u32, generation: u32, seed: u64, rates: &MutationRates) -> Genome {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut specimen = self.specimen.clone();
+        if let Some(mate) = mate {
+            specimen.lsystem = specimen.lsystem.crossover(&mate.specimen.lsystem, &mut rng);
+        }
+        specimen.lsystem = specimen.lsystem.mutate(rates, &mut rng);
+        specimen.seed = rng.random();
+        Genome {
+            id,
+            parents: std::iter::once(self.id).chain(mate.map(|m| m.id)).collect(),
+            generation,
+            seed,
+            rates: *rates,
+            specimen,
+        }
+    }
+}
+
+/// Interactive breeding: a brood of offspring is shown in a grid, the favourites picked from
+/// it parent the next brood
+#[derive(Resource)]
+pub struct Breeding {
+    pub rates: MutationRates,
+    /// Offspring per generation, laid out as a square-ish grid
+    pub brood_size: usize,
+    /// Distance between neighbours in the grid
+    pub spacing: f32,
+    /// Every genome bred so far, indexed by id
+    pub lineage: Vec<Genome>,
+    /// Ids of the offspring on show
+    pub brood: Vec<u32>,
+    /// Ids picked to parent the next generation
+    pub favourites: Vec<u32>,
+    pub generation: u32,
+    /// Respawn the brood on the next frame
+    pub respawn: bool,
+    /// Where the lineage is saved, and how that went
+    pub lineage_path: String,
+    pub status: String,
+    seed: u64,
+}
+
+impl Default for Breeding {
+    fn default() -> Self {
+        Self {
+            rates: MutationRates::default(),
+            brood_size: 9,
+            spacing: 250.0,
+            lineage: Vec::new(),
+            brood: Vec::new(),
+            favourites: Vec::new(),
+            generation: 0,
+            respawn: false,
+            lineage_path: "lineage.ron".to_string(),
+            status: String::new(),
+            seed: 0,
+        }
+    }
+}
+
+impl Breeding {
+    pub fn is_active(&self) -> bool {
+        !self.lineage.is_empty()
+    }
+
+    /// Start a new lineage from `founder` and breed its first brood of mutants
+    pub fn start(&mut self, founder: Specimen) {
+        self.seed = founder.seed;
+        self.lineage = vec![Genome { id: 0, parents: Vec::new(), generation: 0, seed: founder.seed, rates: self.rates, specimen: founder }];
+        self.generation = 0;
+        self.favourites = vec![0];
+        self.status.clear();
+        self.next_generation();
+    }
+
+    /// Breed a new brood from the favourites, each child from one favourite or a cross of two
+    pub fn next_generation(&mut self) {
+        if self.favourites.is_empty() {
+            return;
+        }
+        self.generation += 1;
+        let mut rng = StdRng::seed_from_u64(self.seed ^ (self.generation as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let parents = std::mem::take(&mut self.favourites);
+        self.brood.clear();
+        for _ in 0..self.brood_size.max(1) {
+            let id = self.lineage.len() as u32;
+            let first = parents[rng.random_range(0..parents.len())];
+            let others: Vec<u32> = parents.iter().copied().filter(|&p| p != first).collect();
+            let mate = others.choose(&mut rng).copied();
+            let seed = rng.random();
+            let child = self.lineage[first as usize].offspring(
+                mate.map(|m| &self.lineage[m as usize]),
+                id,
+                self.generation,
+                seed,
+                &self.rates,
+            );
+            self.lineage.push(child);
+            self.brood.push(id);
+        }
+        self.respawn = true;
+    }
+
+    pub fn toggle_favourite(&mut self, id: u32) {
+        match self.favourites.iter().position(|&f| f == id) {
+            Some(i) => {
+                self.favourites.remove(i);
+            }
+            None => self.favourites.push(id),
+        }
+    }
+
+    /// Drop the lineage and bring the meadow back
+    pub fn stop(&mut self) {
+        self.lineage.clear();
+        self.brood.clear();
+        self.favourites.clear();
+        self.respawn = true;
+    }
+
+    /// Breed genome `id` again from its recorded parents, seed and rates
+    #[allow(dead_code)] // reproducibility check for tests and lineage tools
+    pub fn rebreed(&self, id: u32) -> Option<Genome> {
+        let genome = self.lineage.get(id as usize)?;
+        let parent = self.lineage.get(*genome.parents.first()? as usize)?;
+        let mate = genome.parents.get(1).and_then(|&m| self.lineage.get(m as usize));
+        Some(parent.offspring(mate, id, genome.generation, genome.seed, &genome.rates))
+    }
+
+    /// Write the whole lineage as RON
+    pub fn save_lineage(&self, path: &Path) -> Result<(), SpecimenError> {
+        let text = ron::ser::to_string_pretty(&self.lineage, ron::ser::PrettyConfig::default())
+            .map_err(|e| SpecimenError::Ron(e.to_string()))?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Marks the entity showing the genome with this id
+#[derive(Component)]
+pub struct Offspring(pub u32);
+
+type Meadow = Or<(With<EcosystemMember>, With<TerrainMesh>)>;
+
+/// Lay the brood out in a grid around the origin, hiding the meadow while breeding
+pub fn spawn_brood(
+    mut commands: Commands,
+    mut breeding: ResMut<Breeding>,
+    mut selected: ResMut<SelectedPlant>,
+    shown: Query<Entity, With<Offspring>>,
+    mut meadow: Query<&mut Visibility, Meadow>,
+) {
+    if !breeding.respawn {
+        return;
+    }
+    breeding.respawn = false;
+    for entity in &shown {
+        commands.entity(entity).despawn();
+        if selected.0 == Some(entity) {
+            selected.0 = None;
+        }
+    }
+    let visibility = if breeding.is_active() { Visibility::Hidden } else { Visibility::Inherited };
+    for mut shown in &mut meadow {
+        *shown = visibility;
+    }
+
+    let columns = (breeding.brood.len() as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = breeding.brood.len().div_ceil(columns);
+    let centre = Vec2::new(columns as f32 - 1.0, rows as f32 - 1.0) * 0.5;
+    for (i, &id) in breeding.brood.iter().enumerate() {
+        let cell = Vec2::new((i % columns) as f32, (i / columns) as f32) - centre;
+        let entity = commands.spawn((
+            breeding.lineage[id as usize].specimen.to_plant(),
+            Transform::from_xyz(cell.x * breeding.spacing, 0.0, cell.y * breeding.spacing),
+            GlobalTransform::default(),
+            Visibility::default(),
+            Offspring(id),
+            Name::new(format!("#{id}")),
+        )).id();
+        if i == 0 {
+            selected.0 = Some(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::load_preset;
+
+    #[test]
+    fn lineage_rebreeds_exactly() {
+        let mut breeding = Breeding { rates: MutationRates { symbol: 0.8, ..default() }, ..default() };
+        breeding.start(load_preset("Flower").unwrap());
+        breeding.favourites = vec![breeding.brood[1], breeding.brood[4]];
+        breeding.next_generation();
+
+        for &id in &breeding.brood {
+            let genome = &breeding.lineage[id as usize];
+            assert_eq!(genome.parents.len(), 2);
+            let again = breeding.rebreed(id).unwrap();
+            assert!(again.specimen.lsystem.rules == genome.specimen.lsystem.rules);
+            assert_eq!(again.specimen.seed, genome.specimen.seed);
+        }
+    }
+}
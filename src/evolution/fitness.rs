@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::field::GrayImage;
+use crate::light::{LightGrid, ShadowCasters};
+use crate::skeleton::PlantSkeleton;
+use crate::specimen::{Specimen, SpecimenError};
+
+/// Something measured on a grown plant's geometry, higher is better
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    /// Leaf area weighted by the light reaching each leaf through the plant's own canopy
+    LightCapture,
+    /// Highest point above the base
+    Height,
+    /// Total area of the leaf and petal polygons
+    LeafArea,
+    /// 1 when the wood is balanced around the base, towards 0 the more lopsided it is
+    Symmetry,
+    /// Branches, the main axis included
+    BranchCount,
+    /// Overlap (intersection over union, 0..1) of the plant's front view with a grayscale
+    /// image, white inside. The image spans `width` × `height` world units, centred on the
+    /// base with its bottom edge at the ground.
+    Silhouette { path: String, width: f32, height: f32 },
+}
+
+impl Objective {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Objective::LightCapture => "light_capture",
+            Objective::Height => "height",
+            Objective::LeafArea => "leaf_area",
+            Objective::Symmetry => "symmetry",
+            Objective::BranchCount => "branch_count",
+            Objective::Silhouette { .. } => "silhouette",
+        }
+    }
+}
+
+/// One term of the fitness: `weight` × the objective's score. Weights also convert units
+/// (height is in world units, symmetry 0..1); negative weights penalise.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightedObjective {
+    pub objective: Objective,
+    pub weight: f32,
+}
+
+/// Grows specimens and scores them, with silhouette targets loaded once
+pub struct Evaluator {
+    objectives: Vec<WeightedObjective>,
+    silhouettes: Vec<Option<GrayImage>>,
+    /// Longest string a plant may reach while growing before it is given up on
+    max_length: usize,
+}
+
+impl Evaluator {
+    /// Fails if a silhouette can't be loaded or doesn't have a positive size
+    pub fn new(objectives: &[WeightedObjective], max_length: usize) -> Result<Self, SpecimenError> {
+        let silhouettes = objectives.iter().map(|o| match &o.objective {
+            Objective::Silhouette { path, width, height } => {
+                if *width <= 0.0 || *height <= 0.0 {
+                    return Err(SpecimenError::Invalid(format!("silhouette {path} is {width} × {height}")));
+                }
+                GrayImage::load(Path::new(path))
+                    .map(Some)
+                    .map_err(|e| SpecimenError::Invalid(format!("could not load silhouette {path}: {e}")))
+            }
+            _ => Ok(None),
+        }).collect::<Result<_, _>>()?;
+        Ok(Self { objectives: objectives.to_vec(), silhouettes, max_length })
+    }
+
+    /// Score of every objective for the fully grown specimen, or `None` if its string
+    /// outgrew `max_length`
+    pub fn scores(&self, specimen: &Specimen) -> Option<Vec<f32>> {
+        let mut plant = specimen.to_plant();
+        while !plant.finished() {
+            plant.step();
+            if plant.current_string.len() > self.max_length {
+                return None;
+            }
+        }
+        let skeleton = plant.skeleton(None);
+        Some(self.objectives.iter().zip(&self.silhouettes).map(|(o, image)| match &o.objective {
+            Objective::LightCapture => light_capture(&skeleton),
+            Objective::Height => skeleton.height(),
+            Objective::LeafArea => ShadowCasters::from_skeleton(&skeleton).0.iter().map(|&(_, area)| area).sum(),
+            Objective::Symmetry => symmetry(&skeleton),
+            Objective::BranchCount => (0..skeleton.nodes.len()).filter(|&i| !skeleton.continues_parent(i)).count() as f32,
+            Objective::Silhouette { width, height, .. } => {
+                image.as_ref().map_or(0.0, |image| silhouette_overlap(&skeleton, image, Vec2::new(*width, *height)))
+            }
+        }).collect())
+    }
+
+    /// Weighted sum of the scores
+    pub fn fitness(&self, scores: &[f32]) -> f32 {
+        self.objectives.iter().zip(scores).map(|(o, score)| o.weight * score).sum()
+    }
+}
+
+/// Cast the plant's leaves into a light grid fitted around it and sum each leaf's area times
+/// the light falling on it from above
+fn light_capture(skeleton: &PlantSkeleton) -> f32 {
+    let casters = ShadowCasters::from_skeleton(skeleton);
+    let Some((min, max)) = casters.0.iter().fold(None, |bounds: Option<(Vec3, Vec3)>, &(p, _)| match bounds {
+        Some((min, max)) => Some((min.min(p), max.max(p))),
+        None => Some((p, p)),
+    }) else {
+        return 0.0;
+    };
+    let cell_size = ((max - min).max_element() / 24.0).max(0.5);
+    let dims = ((max - min) / cell_size).ceil().as_uvec3() + 3;
+    let mut grid = LightGrid::new(min - cell_size, cell_size, dims);
+    grid.rebuild([(&casters, &GlobalTransform::IDENTITY)]);
+    // Sampled a layer up, so a leaf's own shadow doesn't count against it
+    casters.0.iter().map(|&(centre, area)| area * grid.exposure(centre + Vec3::Y * cell_size)).sum()
+}
+
+/// How close the length-weighted centre of the wood is to the trunk, relative to its spread
+fn symmetry(skeleton: &PlantSkeleton) -> f32 {
+    let (mut centre, mut spread, mut total) = (Vec2::ZERO, 0.0, 0.0);
+    for node in &skeleton.nodes {
+        let (mid, length) = ((node.start + node.position).xz() * 0.5, node.length());
+        centre += mid * length;
+        spread += mid.length() * length;
+        total += length;
+    }
+    if spread <= f32::EPSILON {
+        return 1.0;
+    }
+    (1.0 - (centre / total).length() / (spread / total)).clamp(0.0, 1.0)
+}
+
+/// Rasterise the front view (XY) of the branches and leaves at the target's resolution and
+/// compare it with the target
+fn silhouette_overlap(skeleton: &PlantSkeleton, target: &GrayImage, extent: Vec2) -> f32 {
+    let size = UVec2::new(target.width.min(128), target.height.min(128)).max(UVec2::ONE);
+    let pixel = extent / size.as_vec2();
+    let mut mask = vec![false; (size.x * size.y) as usize];
+    let mut mark = |p: Vec3, radius: f32| {
+        let centre = (Vec2::new(p.x + extent.x * 0.5, p.y) / pixel).as_ivec2();
+        let reach = (Vec2::splat(radius) / pixel).ceil().as_ivec2();
+        for y in centre.y - reach.y..=centre.y + reach.y {
+            for x in centre.x - reach.x..=centre.x + reach.x {
+                if x >= 0 && y >= 0 && (x as u32) < size.x && (y as u32) < size.y {
+                    mask[(y as u32 * size.x + x as u32) as usize] = true;
+                }
+            }
+        }
+    };
+    let step = pixel.min_element() * 0.5;
+    for node in &skeleton.nodes {
+        let samples = (node.length() / step).ceil().max(1.0) as usize;
+        for i in 0..=samples {
+            mark(node.start.lerp(node.position, i as f32 / samples as f32), node.radius);
+        }
+    }
+    for folio in &skeleton.folios {
+        for &vertex in &folio.vertices {
+            mark(vertex, 0.0);
+        }
+    }
+
+    let (mut both, mut either) = (0, 0);
+    for y in 0..size.y {
+        for x in 0..size.x {
+            // Image rows run top down, the mask bottom up
+            let uv = Vec2::new((x as f32 + 0.5) / size.x as f32, 1.0 - (y as f32 + 0.5) / size.y as f32);
+            let inside = target.sample(uv) > 0.5;
+            let drawn = mask[(y * size.x + x) as usize];
+            both += usize::from(inside && drawn);
+            either += usize::from(inside || drawn);
+        }
+    }
+    if either == 0 { 0.0 } else { both as f32 / either as f32 }
+}
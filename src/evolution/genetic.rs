@@ -0,0 +1,249 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::evolution::fitness::{Evaluator, Objective, WeightedObjective};
+use crate::lsystem::MutationRates;
+use crate::presets::load_preset;
+use crate::specimen::{Specimen, SpecimenError};
+
+/// A headless evolution run, as read from a RON file; missing fields take the defaults
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeneticSettings {
+    /// Preset name or specimen file the first population is mutated from
+    pub founder: String,
+    pub population: usize,
+    pub generations: u32,
+    /// Candidates drawn per tournament; the fittest of them becomes a parent
+    pub tournament: usize,
+    /// Fittest candidates copied unchanged into the next generation
+    pub elitism: usize,
+    /// Chance a child is a cross of two parents rather than a copy of one, before mutation
+    pub crossover: f32,
+    pub rates: MutationRates,
+    pub objectives: Vec<WeightedObjective>,
+    /// Longest string a candidate may grow before it is given up on
+    pub max_length: usize,
+    pub seed: u64,
+    /// Directory for `fitness.csv` and the best grammars
+    pub output: String,
+    /// How many of the final population to save
+    pub keep: usize,
+}
+
+impl Default for GeneticSettings {
+    fn default() -> Self {
+        Self {
+            founder: "Flower".to_string(),
+            population: 40,
+            generations: 30,
+            tournament: 3,
+            elitism: 2,
+            crossover: 0.5,
+            rates: MutationRates::default(),
+            objectives: vec![
+                WeightedObjective { objective: Objective::LightCapture, weight: 1.0 },
+                WeightedObjective { objective: Objective::Symmetry, weight: 100.0 },
+            ],
+            max_length: 200_000,
+            seed: 1,
+            output: "evolution".to_string(),
+            keep: 5,
+        }
+    }
+}
+
+impl GeneticSettings {
+    pub fn load(path: &Path) -> Result<Self, SpecimenError> {
+        ron::from_str(&fs::read_to_string(path)?).map_err(|e| SpecimenError::Ron(e.to_string()))
+    }
+}
+
+/// A grammar in the population with its scores; `None` when it outgrew the length limit
+#[derive(Clone)]
+pub struct Candidate {
+    pub specimen: Specimen,
+    pub scores: Option<Vec<f32>>,
+    pub fitness: f32,
+}
+
+/// Fitness of one generation, after evaluation
+pub struct GenerationStats {
+    pub best: f32,
+    /// Over the candidates that grew within the length limit
+    pub mean: f32,
+    pub worst: f32,
+    pub invalid: usize,
+    /// The best candidate's score per objective
+    pub best_scores: Vec<f32>,
+}
+
+/// Genetic algorithm over grammars: tournament selection, crossover, mutation and elitism,
+/// deterministic for the same settings and seed
+pub struct GeneticRun {
+    pub settings: GeneticSettings,
+    /// Sorted fittest first
+    pub population: Vec<Candidate>,
+    pub history: Vec<GenerationStats>,
+    evaluator: Evaluator,
+    rng: StdRng,
+}
+
+impl GeneticRun {
+    /// Evaluate the founder and mutants of it as generation 0
+    pub fn new(settings: GeneticSettings) -> Result<Self, SpecimenError> {
+        let evaluator = Evaluator::new(&settings.objectives, settings.max_length)?;
+        let founder = match load_preset(&settings.founder) {
+            Some(specimen) => specimen,
+            None => Specimen::load(Path::new(&settings.founder))?,
+        };
+        let mut rng = StdRng::seed_from_u64(settings.seed);
+        let mut specimens = vec![founder.clone()];
+        while specimens.len() < settings.population.max(1) {
+            let mut mutant = founder.clone();
+            mutant.lsystem = founder.lsystem.mutate(&settings.rates, &mut rng);
+            specimens.push(mutant);
+        }
+
+        let mut run = Self {
+            evaluator,
+            settings,
+            population: Vec::new(),
+            history: Vec::new(),
+            rng,
+        };
+        run.population = run.evaluate(specimens);
+        run.record();
+        Ok(run)
+    }
+
+    /// Breed and evaluate the next generation
+    pub fn step(&mut self) {
+        let size = self.settings.population.max(1);
+        let elites = self.settings.elitism.min(size);
+        let mut children = Vec::new();
+        while elites + children.len() < size {
+            let mut child = self.tournament().specimen.clone();
+            if self.rng.random::<f32>() < self.settings.crossover {
+                let mate = self.tournament().specimen.lsystem.clone();
+                child.lsystem = child.lsystem.crossover(&mate, &mut self.rng);
+            }
+            child.lsystem = child.lsystem.mutate(&self.settings.rates, &mut self.rng);
+            children.push(child);
+        }
+
+        let mut next = self.population[..elites].to_vec();
+        next.extend(self.evaluate(children));
+        next.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        self.population = next;
+        self.record();
+    }
+
+    /// Fittest of `tournament` candidates drawn at random
+    fn tournament(&mut self) -> &Candidate {
+        let size = self.settings.tournament.max(1);
+        let best = (0..size)
+            .map(|_| self.rng.random_range(0..self.population.len()))
+            .min()
+            .unwrap_or(0);
+        // The population is sorted, so the lowest index wins
+        &self.population[best]
+    }
+
+    /// Grow and score the specimens on every core, returned fittest first
+    fn evaluate(&self, specimens: Vec<Specimen>) -> Vec<Candidate> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = specimens.len().div_ceil(threads).max(1);
+        let scores: Vec<Option<Vec<f32>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = specimens.chunks(chunk)
+                .map(|chunk| scope.spawn(|| chunk.iter().map(|s| self.evaluator.scores(s)).collect::<Vec<_>>()))
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().expect("evaluation thread panicked")).collect()
+        });
+
+        let mut candidates: Vec<Candidate> = specimens.into_iter().zip(scores).map(|(specimen, scores)| {
+            let fitness = scores.as_ref().map_or(f32::NEG_INFINITY, |s| self.evaluator.fitness(s));
+            Candidate { specimen, scores, fitness }
+        }).collect();
+        candidates.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        candidates
+    }
+
+    fn record(&mut self) {
+        let valid: Vec<f32> = self.population.iter().filter(|c| c.scores.is_some()).map(|c| c.fitness).collect();
+        let (best, worst) = valid.iter().fold((f32::MIN, f32::MAX), |(hi, lo), &f| (hi.max(f), lo.min(f)));
+        self.history.push(GenerationStats {
+            best: if valid.is_empty() { 0.0 } else { best },
+            mean: if valid.is_empty() { 0.0 } else { valid.iter().sum::<f32>() / valid.len() as f32 },
+            worst: if valid.is_empty() { 0.0 } else { worst },
+            invalid: self.population.len() - valid.len(),
+            best_scores: self.population.first().and_then(|c| c.scores.clone()).unwrap_or_default(),
+        });
+    }
+}
+
+/// Run the whole evolution without rendering. Writes `fitness.csv` (one row per generation,
+/// flushed as it goes) and the fittest grammars as `best_01.ron`, `best_02.ron`, … to the
+/// output directory.
+pub fn run_genetic(settings: GeneticSettings) -> Result<(), SpecimenError> {
+    let output = Path::new(&settings.output).to_path_buf();
+    fs::create_dir_all(&output)?;
+    let mut csv = BufWriter::new(File::create(output.join("fitness.csv"))?);
+    let names: Vec<&str> = settings.objectives.iter().map(|o| o.objective.name()).collect();
+    writeln!(csv, "generation,best,mean,worst,invalid,{}", names.join(","))?;
+
+    let generations = settings.generations;
+    let mut run = GeneticRun::new(settings)?;
+    for generation in 0..=generations {
+        if generation > 0 {
+            run.step();
+        }
+        let stats = run.history.last().expect("every generation is recorded");
+        let scores: Vec<String> = stats.best_scores.iter().map(|s| s.to_string()).collect();
+        writeln!(csv, "{generation},{},{},{},{},{}", stats.best, stats.mean, stats.worst, stats.invalid, scores.join(","))?;
+        csv.flush()?;
+        println!("generation {generation}: best {:.3}, mean {:.3}", stats.best, stats.mean);
+    }
+
+    for (rank, candidate) in run.population.iter().filter(|c| c.scores.is_some()).take(run.settings.keep).enumerate() {
+        candidate.specimen.save(&output.join(format!("best_{:02}.ron", rank + 1)))?;
+    }
+    println!("wrote {}", output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elitism_never_loses_the_best() {
+        let settings = GeneticSettings {
+            founder: "Fern".to_string(),
+            population: 8,
+            generations: 3,
+            objectives: vec![
+                WeightedObjective { objective: Objective::Height, weight: 1.0 },
+                WeightedObjective { objective: Objective::BranchCount, weight: 0.1 },
+            ],
+            ..GeneticSettings::default()
+        };
+        let evolve = || {
+            let mut run = GeneticRun::new(settings.clone()).unwrap();
+            for _ in 0..settings.generations {
+                run.step();
+            }
+            run.history.iter().map(|stats| stats.best).collect::<Vec<_>>()
+        };
+
+        let best = evolve();
+        assert_eq!(best.len(), 4);
+        assert!(best.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert_eq!(best, evolve());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod evolution;
+pub mod fitness;
+pub mod genetic;
+
+pub use evolution::{spawn_brood, Breeding, Offspring};
+pub use genetic::{run_genetic, GeneticSettings};
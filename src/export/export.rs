@@ -0,0 +1,281 @@
+use std::{fs, io, path::Path};
+
+use bevy::{
+    mesh::{Indices, MeshVertexAttribute, VertexAttributeValues},
+    prelude::*,
+};
+use serde_json::{json, Value};
+
+use crate::draw::draw::{build_folio_mesh, build_segment_mesh};
+use crate::material::{SurfaceKind, SurfaceMaterial};
+use crate::plant::Plant;
+use crate::skeleton::rig::Rig;
+use crate::wind::wind::branch_sway;
+
+const FLOAT: u32 = 5126;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const ATTRIBUTES: [(MeshVertexAttribute, &str); 6] = [
+    (Mesh::ATTRIBUTE_POSITION, "POSITION"),
+    (Mesh::ATTRIBUTE_NORMAL, "NORMAL"),
+    (Mesh::ATTRIBUTE_UV_0, "TEXCOORD_0"),
+    (Mesh::ATTRIBUTE_COLOR, "COLOR_0"),
+    (Mesh::ATTRIBUTE_JOINT_INDEX, "JOINTS_0"),
+    (Mesh::ATTRIBUTE_JOINT_WEIGHT, "WEIGHTS_0"),
+];
+
+/// Binary buffer plus the buffer views and accessors that describe it
+#[derive(Default)]
+struct GlbBuffer {
+    bin: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GlbBuffer {
+    fn accessor(&mut self, bytes: &[u8], target: Option<u32>, mut accessor: Value) -> usize {
+        let mut view = json!({ "buffer": 0, "byteOffset": self.bin.len(), "byteLength": bytes.len() });
+        if let Some(target) = target {
+            view["target"] = target.into();
+        }
+        self.bin.extend_from_slice(bytes);
+        // Every view starts 4-byte aligned
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+        self.views.push(view);
+
+        accessor["bufferView"] = (self.views.len() - 1).into();
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn attribute(&mut self, values: &VertexAttributeValues) -> Option<usize> {
+        let (component, kind) = match values {
+            VertexAttributeValues::Float32x2(_) => (FLOAT, "VEC2"),
+            VertexAttributeValues::Float32x3(_) => (FLOAT, "VEC3"),
+            VertexAttributeValues::Float32x4(_) => (FLOAT, "VEC4"),
+            VertexAttributeValues::Uint16x4(_) => (UNSIGNED_SHORT, "VEC4"),
+            _ => return None,
+        };
+        let mut accessor = json!({ "componentType": component, "count": values.len(), "type": kind });
+        // glTF requires bounds on positions
+        if let VertexAttributeValues::Float32x3(positions) = values {
+            let (min, max) = positions.iter().map(|p| Vec3::from_array(*p))
+                .fold((Vec3::MAX, Vec3::MIN), |(min, max), p| (min.min(p), max.max(p)));
+            accessor["min"] = json!(min.to_array());
+            accessor["max"] = json!(max.to_array());
+        }
+        Some(self.accessor(values.get_bytes(), Some(ARRAY_BUFFER), accessor))
+    }
+
+    fn primitive(&mut self, mesh: &Mesh, material: usize) -> Option<Value> {
+        let Some(Indices::U32(indices)) = mesh.indices() else { return None };
+        let mut attributes = serde_json::Map::new();
+        for (attribute, name) in ATTRIBUTES {
+            if let Some(accessor) = mesh.attribute(attribute).and_then(|v| self.attribute(v)) {
+                attributes.insert(name.to_string(), accessor.into());
+            }
+        }
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let indices = self.accessor(
+            &bytes,
+            Some(ELEMENT_ARRAY_BUFFER),
+            json!({ "componentType": UNSIGNED_INT, "count": indices.len(), "type": "SCALAR" }),
+        );
+        Some(json!({ "attributes": attributes, "indices": indices, "material": material }))
+    }
+}
+
+fn material_json(name: &str, material: &SurfaceMaterial) -> Value {
+    json!({
+        "name": name,
+        "pbrMetallicRoughness": {
+            "baseColorFactor": material.base_color.to_linear().to_f32_array(),
+            "metallicFactor": material.metallic,
+            "roughnessFactor": material.perceptual_roughness,
+        },
+        "doubleSided": material.double_sided,
+        "alphaMode": if material.alpha_blend { "BLEND" } else { "OPAQUE" },
+    })
+}
+
+/// Write the plant's full-detail mesh as binary glTF; rigged plants also get their joints and skin.
+/// `volume` is the plant's loaded attractor mesh, if it colonizes one.
+pub fn export_glb(plant: &Plant, volume: Option<&Mesh>, path: &Path) -> io::Result<()> {
+    let skeleton = plant.skeleton(volume);
+    let rig = (plant.rig.enabled && !skeleton.nodes.is_empty()).then(|| Rig::new(&skeleton, &plant.rig));
+    let sway = branch_sway(&skeleton);
+    let surfaces = &plant.materials;
+
+    let parts = [
+        ("Bark", build_segment_mesh(&skeleton, &sway, surfaces.texel_density, plant.lod.full_sides, rig.as_ref()), &surfaces.bark),
+        ("Leaves", build_folio_mesh(&skeleton, SurfaceKind::Leaf, &sway, rig.as_ref()), &surfaces.leaf),
+        ("Petals", build_folio_mesh(&skeleton, SurfaceKind::Petal, &sway, rig.as_ref()), &surfaces.petal),
+    ];
+
+    let mut buffer = GlbBuffer::default();
+    let mut materials = Vec::new();
+    let mut primitives = Vec::new();
+    for (name, mesh, surface) in &parts {
+        if mesh.count_vertices() == 0 {
+            continue;
+        }
+        materials.push(material_json(name, surface));
+        primitives.extend(buffer.primitive(mesh, materials.len() - 1));
+    }
+    if primitives.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "plant has no geometry"));
+    }
+
+    // Node 0 is the plant, node 1 its mesh, and joints follow in rig order
+    let mut nodes = vec![json!({ "name": "Plant", "children": [1] }), json!({ "name": "PlantMesh", "mesh": 0 })];
+    let mut skins = Vec::new();
+    if let Some(rig) = &rig {
+        for i in 0..rig.joints.len() {
+            let local = rig.local_transform(i);
+            nodes.push(json!({
+                "name": format!("Joint{i}"),
+                "translation": local.translation.to_array(),
+                "rotation": local.rotation.to_array(),
+            }));
+        }
+        for (i, joint) in rig.joints.iter().enumerate() {
+            let parent = joint.parent.map_or(0, |p| p + 2);
+            let children = nodes[parent].as_object_mut().unwrap().entry("children").or_insert(json!([]));
+            children.as_array_mut().unwrap().push((i + 2).into());
+        }
+
+        let matrices: Vec<u8> = rig.inverse_bindposes().iter()
+            .flat_map(|m| m.to_cols_array())
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let inverse_bind_matrices = buffer.accessor(
+            &matrices,
+            None,
+            json!({ "componentType": FLOAT, "count": rig.joints.len(), "type": "MAT4" }),
+        );
+        skins.push(json!({
+            "inverseBindMatrices": inverse_bind_matrices,
+            "joints": (2..rig.joints.len() + 2).collect::<Vec<_>>(),
+        }));
+        nodes[1]["skin"] = 0.into();
+    }
+
+    let mut document = json!({
+        "asset": { "version": "2.0", "generator": "bevy-l-system" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": nodes,
+        "meshes": [{ "name": "Plant", "primitives": primitives }],
+        "materials": materials,
+        "buffers": [{ "byteLength": buffer.bin.len() }],
+        "bufferViews": buffer.views,
+        "accessors": buffer.accessors,
+    });
+    if !skins.is_empty() {
+        document["skins"] = skins.into();
+    }
+
+    fs::write(path, glb(&serde_json::to_vec(&document)?, &buffer.bin))
+}
+
+/// Pack the JSON and binary chunks into a `.glb` container
+fn glb(json: &[u8], bin: &[u8]) -> Vec<u8> {
+    let json_len = json.len().next_multiple_of(4);
+    let bin_len = bin.len().next_multiple_of(4);
+    let total = 12 + 8 + json_len + 8 + bin_len;
+
+    let mut out = Vec::with_capacity(total);
+    for word in [0x4654_6C67, 2, total as u32] {
+        out.extend_from_slice(&u32::to_le_bytes(word));
+    }
+    out.extend_from_slice(&(json_len as u32).to_le_bytes());
+    out.extend_from_slice(b"JSON");
+    out.extend_from_slice(json);
+    out.resize(20 + json_len, b' ');
+    out.extend_from_slice(&(bin_len as u32).to_le_bytes());
+    out.extend_from_slice(b"BIN\0");
+    out.extend_from_slice(bin);
+    out.resize(total, 0);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsystem::LSystem;
+    use crate::presets::load_preset;
+
+    fn word(bytes: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn glb_chunks_and_accessors_line_up() {
+        let mut plant = load_preset("Flower").unwrap().to_plant();
+        plant.go_to(3);
+        for rigged in [false, true] {
+            plant.rig.enabled = rigged;
+            let path = std::env::temp_dir().join(format!("bevy_lsystem_export_{}_{rigged}.glb", std::process::id()));
+            export_glb(&plant, None, &path).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(&bytes[0..4], b"glTF");
+            assert_eq!(word(&bytes, 4), 2);
+            assert_eq!(word(&bytes, 8), bytes.len());
+
+            let json_len = word(&bytes, 12);
+            assert_eq!(&bytes[16..20], b"JSON");
+            assert_eq!(json_len % 4, 0);
+            let document: Value = serde_json::from_slice(&bytes[20..20 + json_len]).unwrap();
+            let bin_at = 20 + json_len;
+            let bin_len = word(&bytes, bin_at);
+            assert_eq!(&bytes[bin_at + 4..bin_at + 8], b"BIN\0");
+            assert_eq!(bin_len % 4, 0);
+            assert_eq!(bin_at + 8 + bin_len, bytes.len());
+            assert!(document["buffers"][0]["byteLength"].as_u64().unwrap() as usize <= bin_len);
+
+            let views = document["bufferViews"].as_array().unwrap();
+            for accessor in document["accessors"].as_array().unwrap() {
+                let view = &views[accessor["bufferView"].as_u64().unwrap() as usize];
+                let components = match accessor["type"].as_str().unwrap() {
+                    "SCALAR" => 1,
+                    "VEC2" => 2,
+                    "VEC3" => 3,
+                    "VEC4" => 4,
+                    "MAT4" => 16,
+                    other => panic!("unexpected accessor type {other}"),
+                };
+                let size = if accessor["componentType"] == UNSIGNED_SHORT { 2 } else { 4 };
+                let (offset, length) = (view["byteOffset"].as_u64().unwrap() as usize, view["byteLength"].as_u64().unwrap() as usize);
+                assert_eq!(offset % 4, 0);
+                assert_eq!(length, accessor["count"].as_u64().unwrap() as usize * components * size);
+                assert!(offset + length <= bin_len);
+            }
+
+            let primitives = document["meshes"][0]["primitives"].as_array().unwrap();
+            assert!(!primitives.is_empty());
+            for primitive in primitives {
+                let counts: Vec<&Value> = primitive["attributes"].as_object().unwrap().values()
+                    .map(|a| &document["accessors"][a.as_u64().unwrap() as usize]["count"])
+                    .collect();
+                assert!(counts.windows(2).all(|pair| pair[0] == pair[1]));
+            }
+            assert_eq!(document.get("skins").is_some(), rigged);
+        }
+    }
+
+    #[test]
+    fn bare_plants_are_not_exported() {
+        let flower = load_preset("Flower").unwrap().to_plant();
+        let plant = Plant::new(LSystem::new("+-", Vec::new(), 25.0), 1.0, 3, 1.0, flower.palette.clone());
+        let path = std::env::temp_dir().join(format!("bevy_lsystem_export_bare_{}.glb", std::process::id()));
+
+        let error = export_glb(&plant, None, &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
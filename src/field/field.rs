@@ -0,0 +1,94 @@
+use std::{fs, io, path::Path};
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::{CompressedImageFormats, ImageSampler, ImageType};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+
+/// Smooth value noise in 0..1, from hashed lattice values
+fn value_noise(p: Vec2, seed: u64) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    // Smoothstep so the gradient is continuous across cells
+    let t = f * f * (Vec2::splat(3.0) - 2.0 * f);
+    let corner = |dx: f32, dy: f32| lattice(cell + Vec2::new(dx, dy), seed);
+    let bottom = corner(0.0, 0.0) + (corner(1.0, 0.0) - corner(0.0, 0.0)) * t.x;
+    let top = corner(0.0, 1.0) + (corner(1.0, 1.0) - corner(0.0, 1.0)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+/// Fractal sum of `octaves` noise layers, each twice the frequency and half the amplitude; 0..1
+pub fn fbm(p: Vec2, octaves: u32, seed: u64) -> f32 {
+    let (mut sum, mut amplitude, mut total, mut frequency) = (0.0, 1.0, 0.0, 1.0);
+    for octave in 0..octaves.max(1) {
+        sum += value_noise(p * frequency, seed.wrapping_add(octave as u64)) * amplitude;
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum / total
+}
+
+fn lattice(cell: Vec2, seed: u64) -> f32 {
+    // SplitMix64 finaliser over the cell coordinates
+    let mut h = seed
+        ^ (cell.x as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (cell.y as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^= h >> 31;
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Single-channel image in 0..1, keeping 16-bit precision when the file has it
+pub struct GrayImage {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl GrayImage {
+    /// Read a PNG (or other format Bevy decodes); colour images use their first channel
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+        let image = Image::from_buffer(
+            &bytes,
+            ImageType::Extension(extension),
+            CompressedImageFormats::NONE,
+            false,
+            ImageSampler::Default,
+            RenderAssetUsages::MAIN_WORLD,
+        )
+        .map_err(io::Error::other)?;
+
+        let data = image.data.as_deref().unwrap_or_default();
+        let values: Vec<f32> = match image.texture_descriptor.format {
+            // Bevy keeps 16-bit grayscale as integer formats
+            TextureFormat::R16Uint => data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]) as f32 / 65535.0).collect(),
+            TextureFormat::Rg16Uint => data.chunks_exact(4).map(|c| u16::from_le_bytes([c[0], c[1]]) as f32 / 65535.0).collect(),
+            TextureFormat::Rgba16Unorm => data.chunks_exact(8).map(|c| u16::from_le_bytes([c[0], c[1]]) as f32 / 65535.0).collect(),
+            // 8-bit images, grayscale included, arrive as RGBA
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => data.chunks_exact(4).map(|c| c[0] as f32 / 255.0).collect(),
+            TextureFormat::Rgba32Float => data.chunks_exact(16).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect(),
+            format => return Err(io::Error::other(format!("unsupported pixel format {format:?}"))),
+        };
+        Ok(Self { width: image.width(), height: image.height(), values })
+    }
+
+    /// Bilinear sample at `uv` in 0..1, clamped to the edges
+    pub fn sample(&self, uv: Vec2) -> f32 {
+        if self.values.is_empty() {
+            return 0.0;
+        }
+        let max = UVec2::new(self.width, self.height).as_vec2() - 1.0;
+        let p = (uv.clamp(Vec2::ZERO, Vec2::ONE) * max).max(Vec2::ZERO);
+        let (x0, y0) = (p.x.floor() as u32, p.y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let at = |x: u32, y: u32| self.values[(y * self.width + x) as usize];
+        let f = p - p.floor();
+        let bottom = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * f.x;
+        let top = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * f.x;
+        bottom + (top - bottom) * f.y
+    }
+}
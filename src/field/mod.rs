@@ -0,0 +1,3 @@
+pub mod field;
+
+pub use field::{fbm, GrayImage};
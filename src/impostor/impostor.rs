@@ -0,0 +1,345 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    camera::visibility::VisibilityRange,
+    math::Affine2,
+    mesh::{Indices, VertexAttributeValues},
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    tasks::{futures::check_ready, AsyncComputeTaskPool, Task},
+};
+use std::f32::consts::TAU;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::draw::lod::Billboard;
+use crate::plant::Plant;
+
+/// Baked far-distance views of a plant, one atlas cell per azimuth
+pub struct ImpostorAtlas {
+    pub views: u32,
+    pub resolution: u32,
+    /// RGBA8 sRGB, `views * resolution` wide and `resolution` high; alpha 0 where empty
+    pub color: Vec<u8>,
+    /// View-space normals packed into RGB; matches the tangent frame of a camera-facing quad
+    pub normal: Vec<u8>,
+    /// Largest horizontal distance from the plant's vertical axis
+    pub radius: f32,
+    pub min_y: f32,
+    pub max_y: f32,
+}
+
+/// Billboard showing an impostor atlas; `current` is the cell shown last
+#[derive(Component)]
+pub struct Impostor {
+    pub views: u32,
+    pub current: Option<u32>,
+}
+
+impl Impostor {
+    /// Cell to show for a camera at `yaw` around the plant (same convention as the bake)
+    pub fn view_for_yaw(&self, yaw: f32) -> u32 {
+        ((yaw / TAU * self.views as f32).round() as i64).rem_euclid(self.views as i64) as u32
+    }
+
+    /// Material UV transform selecting one cell of the atlas
+    pub fn uv_transform(&self, view: u32) -> Affine2 {
+        let scale = 1.0 / self.views as f32;
+        Affine2::from_scale_angle_translation(Vec2::new(scale, 1.0), 0.0, Vec2::new(view as f32 * scale, 0.0))
+    }
+}
+
+/// A plant's last baked impostor, reused while what it was baked from stays the same
+#[derive(Component)]
+pub struct ImpostorCache {
+    /// `impostor_key` of the bake
+    pub key: u64,
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub views: u32,
+}
+
+/// An impostor bake running on the async compute pool; inserting a newer one drops, and so
+/// cancels, this one
+#[derive(Component)]
+pub struct ImpostorBake {
+    /// `impostor_key` of what is being baked
+    pub key: u64,
+    task: Task<Option<ImpostorAtlas>>,
+}
+
+impl ImpostorBake {
+    pub fn start(key: u64, parts: Vec<(Mesh, Color)>, views: u32, resolution: u32) -> Self {
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let parts: Vec<(&Mesh, Color)> = parts.iter().map(|(mesh, color)| (mesh, *color)).collect();
+            bake_impostor(&parts, views, resolution)
+        });
+        Self { key, task }
+    }
+}
+
+/// Billboard child of `plant` showing its cached impostor
+pub fn spawn_billboard(commands: &mut Commands, plant: Entity, cache: &ImpostorCache, range: VisibilityRange) {
+    commands.spawn((
+        Mesh3d(cache.mesh.clone()),
+        MeshMaterial3d(cache.material.clone()),
+        Transform::default(),
+        GlobalTransform::default(),
+        Visibility::default(),
+        Billboard,
+        Impostor { views: cache.views, current: None },
+        range,
+        Name::new("PlantBillboard"),
+        ChildOf(plant),
+    ));
+}
+
+/// Turn finished bakes into the plant's impostor, replacing the billboard it showed before
+pub fn finish_impostor_bakes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut bakes: Query<(Entity, &Plant, &mut ImpostorBake, Option<&Children>)>,
+    billboards: Query<(), With<Billboard>>,
+) {
+    for (entity, plant, mut bake, children) in &mut bakes {
+        let Some(atlas) = check_ready(&mut bake.task) else { continue };
+        commands.entity(entity).remove::<ImpostorBake>();
+        for &child in children.into_iter().flatten() {
+            if billboards.contains(child) {
+                commands.entity(child).despawn();
+            }
+        }
+        let Some(atlas) = atlas else {
+            commands.entity(entity).remove::<ImpostorCache>();
+            continue;
+        };
+
+        let material = materials.add(StandardMaterial {
+            base_color_texture: Some(images.add(atlas.color_image())),
+            normal_map_texture: Some(images.add(atlas.normal_image())),
+            perceptual_roughness: 1.0,
+            alpha_mode: AlphaMode::Mask(0.5),
+            double_sided: true,
+            cull_mode: None,
+            uv_transform: Impostor { views: atlas.views, current: None }.uv_transform(0),
+            ..default()
+        });
+        let cache = ImpostorCache { key: bake.key, mesh: meshes.add(atlas.quad_mesh()), material, views: atlas.views };
+        spawn_billboard(&mut commands, entity, &cache, plant.lod.range(3));
+        commands.entity(entity).insert(cache);
+    }
+}
+
+/// Hash of everything `bake_impostor` reads, to tell whether a redraw needs a new bake
+pub fn impostor_key(parts: &[(&Mesh, Color)], views: u32, resolution: u32) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (views, resolution).hash(&mut hasher);
+    for (mesh, color) in parts {
+        color.to_srgba().to_u8_array().hash(&mut hasher);
+        for attribute in [Mesh::ATTRIBUTE_POSITION, Mesh::ATTRIBUTE_NORMAL, Mesh::ATTRIBUTE_COLOR] {
+            if let Some(values) = mesh.attribute(attribute) {
+                values.get_bytes().hash(&mut hasher);
+            }
+        }
+        if let Some(Indices::U32(indices)) = mesh.indices() {
+            indices.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+struct Vertex {
+    position: Vec3,
+    normal: Vec3,
+    color: Vec4,
+}
+
+fn mesh_triangles(mesh: &Mesh, tint: Vec4, out: &mut Vec<[Vertex; 3]>) {
+    let (
+        Some(VertexAttributeValues::Float32x3(positions)),
+        Some(VertexAttributeValues::Float32x3(normals)),
+    ) = (mesh.attribute(Mesh::ATTRIBUTE_POSITION), mesh.attribute(Mesh::ATTRIBUTE_NORMAL))
+    else {
+        return;
+    };
+    let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => Some(colors),
+        _ => None,
+    };
+    let Some(Indices::U32(indices)) = mesh.indices() else { return };
+
+    let vertex = |i: u32| {
+        let i = i as usize;
+        Vertex {
+            position: Vec3::from_array(positions[i]),
+            normal: Vec3::from_array(normals[i]),
+            color: colors.map_or(tint, |c| Vec4::from_array(c[i]) * tint),
+        }
+    };
+    for tri in indices.chunks_exact(3) {
+        out.push([vertex(tri[0]), vertex(tri[1]), vertex(tri[2])]);
+    }
+}
+
+/// Rasterise `parts` (mesh and material base colour) orthographically from `views`
+/// azimuths around +Y on the CPU. Deterministic and GPU-free, so it also runs headless.
+pub fn bake_impostor(parts: &[(&Mesh, Color)], views: u32, resolution: u32) -> Option<ImpostorAtlas> {
+    let views = views.max(1);
+    let resolution = resolution.max(1);
+
+    let mut triangles = Vec::new();
+    for (mesh, tint) in parts {
+        mesh_triangles(mesh, Vec4::from_array(tint.to_linear().to_f32_array()), &mut triangles);
+    }
+    if triangles.is_empty() {
+        return None;
+    }
+
+    let points = triangles.iter().flatten().map(|v| v.position);
+    let (radius, min_y, max_y) = points.fold((0.0_f32, f32::MAX, f32::MIN), |(r, lo, hi), p| {
+        (r.max(Vec2::new(p.x, p.z).length()), lo.min(p.y), hi.max(p.y))
+    });
+    let radius = radius.max(f32::EPSILON);
+    let height = (max_y - min_y).max(f32::EPSILON);
+
+    let width = (views * resolution) as usize;
+    let res = resolution as f32;
+    let mut color = vec![0u8; width * resolution as usize * 4];
+    let mut normal = vec![0u8; width * resolution as usize * 4];
+    let mut depth = vec![f32::MIN; width * resolution as usize];
+
+    for view in 0..views {
+        let yaw = view as f32 / views as f32 * TAU;
+        let toward_camera = Vec3::new(yaw.sin(), 0.0, yaw.cos());
+        let right = Vec3::new(yaw.cos(), 0.0, -yaw.sin());
+        let cell_x = (view * resolution) as f32;
+
+        // Pixel coordinates (x right, y down) and depth (larger is nearer)
+        let project = |p: Vec3| {
+            Vec3::new(
+                (p.dot(right) + radius) / (2.0 * radius) * res,
+                (max_y - p.y) / height * res,
+                p.dot(toward_camera),
+            )
+        };
+
+        for tri in &triangles {
+            let [a, b, c] = [project(tri[0].position), project(tri[1].position), project(tri[2].position)];
+            let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+            if area.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let x0 = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+            let x1 = (a.x.max(b.x).max(c.x).ceil() as u32).min(resolution);
+            let y0 = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+            let y1 = (a.y.max(b.y).max(c.y).ceil() as u32).min(resolution);
+
+            for py in y0..y1 {
+                for px in x0..x1 {
+                    let (sx, sy) = (px as f32 + 0.5, py as f32 + 0.5);
+                    let w0 = ((b.x - sx) * (c.y - sy) - (b.y - sy) * (c.x - sx)) / area;
+                    let w1 = ((c.x - sx) * (a.y - sy) - (c.y - sy) * (a.x - sx)) / area;
+                    let w2 = 1.0 - w0 - w1;
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+
+                    let index = py as usize * width + (cell_x as usize + px as usize);
+                    let z = a.z * w0 + b.z * w1 + c.z * w2;
+                    if z <= depth[index] {
+                        continue;
+                    }
+                    depth[index] = z;
+
+                    let rgba = tri[0].color * w0 + tri[1].color * w1 + tri[2].color * w2;
+                    let srgb = Color::linear_rgba(rgba.x, rgba.y, rgba.z, 1.0).to_srgba().to_u8_array();
+                    color[index * 4..index * 4 + 4].copy_from_slice(&srgb);
+
+                    // Double-sided leaves: always use the side facing the camera
+                    let mut n = (tri[0].normal * w0 + tri[1].normal * w1 + tri[2].normal * w2).normalize_or(toward_camera);
+                    if n.dot(toward_camera) < 0.0 {
+                        n = -n;
+                    }
+                    let view_normal = Vec3::new(n.dot(right), n.y, n.dot(toward_camera));
+                    let packed = (view_normal * 0.5 + 0.5) * 255.0;
+                    normal[index * 4..index * 4 + 4].copy_from_slice(&[packed.x as u8, packed.y as u8, packed.z as u8, 255]);
+                }
+            }
+        }
+    }
+
+    Some(ImpostorAtlas { views, resolution, color, normal, radius, min_y, max_y })
+}
+
+impl ImpostorAtlas {
+    fn image(&self, data: Vec<u8>, format: TextureFormat) -> Image {
+        Image::new(
+            Extent3d { width: self.views * self.resolution, height: self.resolution, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            data,
+            format,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+    }
+
+    pub fn color_image(&self) -> Image {
+        self.image(self.color.clone(), TextureFormat::Rgba8UnormSrgb)
+    }
+
+    pub fn normal_image(&self) -> Image {
+        self.image(self.normal.clone(), TextureFormat::Rgba8Unorm)
+    }
+
+    /// Quad covering one view, with its origin at the plant root so it yaws about the trunk
+    pub fn quad_mesh(&self) -> Mesh {
+        let mut mesh = Mesh::from(Rectangle::new(self.radius * 2.0, self.max_y - self.min_y))
+            .translated_by(Vec3::new(0.0, (self.min_y + self.max_y) * 0.5, 0.0));
+        if let Err(e) = mesh.generate_tangents() {
+            warn!("could not generate impostor tangents: {e}");
+        }
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(atlas: &ImpostorAtlas, data: &[u8], view: u32, x: u32, y: u32) -> [u8; 4] {
+        let index = (y * atlas.views * atlas.resolution + view * atlas.resolution + x) as usize * 4;
+        data[index..index + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn bake_covers_every_view_from_its_own_side() {
+        // Off the trunk axis, so the box shows on the right from the front and the left from behind
+        let mesh = Mesh::from(Cuboid::new(2.0, 10.0, 2.0)).translated_by(Vec3::new(3.0, 0.0, 0.0));
+        let atlas = bake_impostor(&[(&mesh, Color::WHITE)], 4, 16).unwrap();
+
+        let masks: Vec<Vec<bool>> = (0..4)
+            .map(|view| (0..16 * 16).map(|i| pixel(&atlas, &atlas.color, view, i % 16, i / 16)[3] > 0).collect())
+            .collect();
+        for (view, mask) in masks.iter().enumerate() {
+            assert!(mask.contains(&true), "view {view} is empty");
+        }
+        assert_ne!(masks[0], masks[2]);
+        assert!(pixel(&atlas, &atlas.color, 0, 14, 8)[3] > 0 && pixel(&atlas, &atlas.color, 0, 1, 8)[3] == 0);
+        assert!(pixel(&atlas, &atlas.color, 2, 1, 8)[3] > 0 && pixel(&atlas, &atlas.color, 2, 14, 8)[3] == 0);
+    }
+
+    #[test]
+    fn baked_normals_face_out_of_the_view() {
+        let mesh = Mesh::from(Sphere::new(1.0));
+        let atlas = bake_impostor(&[(&mesh, Color::WHITE)], 4, 32).unwrap();
+
+        for view in 0..4 {
+            let normal = |x, y| pixel(&atlas, &atlas.normal, view, x, y);
+            assert!(normal(26, 16)[0] > 160, "right edge should point right in view {view}");
+            assert!(normal(5, 16)[0] < 96, "left edge should point left in view {view}");
+            assert!(normal(16, 5)[1] > 160, "top should point up in view {view}");
+            assert!(normal(16, 26)[1] < 96, "bottom should point down in view {view}");
+            assert!(normal(16, 16)[2] > 240, "centre should face the camera in view {view}");
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod impostor;
+
+pub use impostor::{finish_impostor_bakes, impostor_key, spawn_billboard, Impostor, ImpostorBake, ImpostorCache};
@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+
+use crate::ecosystem::EcosystemSettings;
+use crate::environment::{Environment, EnvironmentKind, Request};
+use crate::plant::Plant;
+use crate::skeleton::PlantSkeleton;
+
+/// Leaves of one plant as (centre, area) in plant space, refreshed whenever it is drawn
+#[derive(Component, Default, Clone)]
+pub struct ShadowCasters(pub Vec<(Vec3, f32)>);
+
+impl ShadowCasters {
+    pub fn from_skeleton(skeleton: &PlantSkeleton) -> Self {
+        Self(skeleton.folios.iter().map(|folio| {
+            let centre = folio.vertices.iter().sum::<Vec3>() / folio.vertices.len() as f32;
+            // Fan triangulation around the first vertex, like the leaf mesh
+            let area = folio.vertices.windows(2).skip(1)
+                .map(|pair| (pair[0] - folio.vertices[0]).cross(pair[1] - folio.vertices[0]).length() * 0.5)
+                .sum();
+            (centre, area)
+        }).collect())
+    }
+}
+
+/// Voxel grid of shadow cast by every plant's leaves, lit from straight above.
+/// Each leaf shades a pyramid of voxels below it, fading per layer (Palubicki et al. 2009),
+/// and exposure is `exp(-shadow)`: 1 in the open, towards 0 deep in a canopy.
+#[derive(Resource, Clone)]
+pub struct LightGrid {
+    /// World-space minimum corner
+    pub origin: Vec3,
+    pub cell_size: f32,
+    pub dims: UVec3,
+    /// Shadow per unit of leaf area per voxel face area
+    pub leaf_opacity: f32,
+    /// Factor applied to the shadow for every voxel layer further down
+    pub falloff: f32,
+    /// Layers below a leaf that receive its shadow
+    pub shadow_depth: u32,
+    shadow: Vec<f32>,
+}
+
+impl Default for LightGrid {
+    fn default() -> Self {
+        Self::for_ecosystem(&EcosystemSettings::default())
+    }
+}
+
+/// How far plants may spread past the ecosystem's region
+const REGION_MARGIN: f32 = 100.0;
+/// Room above the highest ground for the tallest canopy
+const CANOPY_HEIGHT: f32 = 480.0;
+/// Finest voxel size, coarsened so no axis of a large region exceeds `MAX_CELLS`
+const MIN_CELL_SIZE: f32 = 10.0;
+const MAX_CELLS: f32 = 160.0;
+
+impl LightGrid {
+    /// A grid spanning the ecosystem's region and everything that may grow on its terrain
+    pub fn for_ecosystem(settings: &EcosystemSettings) -> Self {
+        let mut grid = Self::new(Vec3::ZERO, MIN_CELL_SIZE, UVec3::ONE);
+        grid.fit_ecosystem(settings);
+        grid
+    }
+
+    /// Resize to cover the ecosystem, keeping the shading parameters; the shadow is cleared
+    pub fn fit_ecosystem(&mut self, settings: &EcosystemSettings) {
+        let terrain = &settings.terrain;
+        let min = settings.region_min.extend(terrain.base).xzy() - Vec3::new(REGION_MARGIN, 0.0, REGION_MARGIN);
+        let max = settings.region_max.extend(terrain.base + terrain.relief + CANOPY_HEIGHT).xzy()
+            + Vec3::new(REGION_MARGIN, 0.0, REGION_MARGIN);
+        let extent = (max - min).max(Vec3::ONE);
+        self.origin = min;
+        self.cell_size = MIN_CELL_SIZE.max(extent.max_element() / MAX_CELLS);
+        self.dims = (extent / self.cell_size).ceil().as_uvec3().max(UVec3::ONE);
+        self.shadow = vec![0.0; (self.dims.x * self.dims.y * self.dims.z) as usize];
+    }
+
+    pub fn new(origin: Vec3, cell_size: f32, dims: UVec3) -> Self {
+        Self {
+            origin,
+            cell_size,
+            dims,
+            leaf_opacity: 0.25,
+            falloff: 0.6,
+            shadow_depth: 8,
+            shadow: vec![0.0; (dims.x * dims.y * dims.z) as usize],
+        }
+    }
+
+    fn coords(&self, position: Vec3) -> IVec3 {
+        ((position - self.origin) / self.cell_size).floor().as_ivec3()
+    }
+
+    fn index(&self, cell: IVec3) -> Option<usize> {
+        let dims = self.dims.as_ivec3();
+        (cell.cmpge(IVec3::ZERO).all() && cell.cmplt(dims).all())
+            .then(|| (cell.x + dims.x * (cell.y + dims.y * cell.z)) as usize)
+    }
+
+    pub fn clear(&mut self) {
+        self.shadow.fill(0.0);
+    }
+
+    /// Shade the pyramid below a leaf of `area` at world `position`
+    pub fn cast(&mut self, position: Vec3, area: f32) {
+        let cell = self.coords(position);
+        let mut shade = self.leaf_opacity * area / (self.cell_size * self.cell_size);
+        for layer in 0..=self.shadow_depth as i32 {
+            for dx in -layer..=layer {
+                for dz in -layer..=layer {
+                    if let Some(i) = self.index(cell + IVec3::new(dx, -layer, dz)) {
+                        self.shadow[i] += shade;
+                    }
+                }
+            }
+            shade *= self.falloff;
+        }
+    }
+
+    /// Recast every plant's leaves from scratch
+    pub fn rebuild<'a>(&mut self, plants: impl IntoIterator<Item = (&'a ShadowCasters, &'a GlobalTransform)>) {
+        self.clear();
+        for (casters, transform) in plants {
+            for &(centre, area) in &casters.0 {
+                self.cast(transform.transform_point(centre), area);
+            }
+        }
+    }
+
+    /// Light reaching world `position`, 0..=1; outside the grid is open sky
+    pub fn exposure(&self, position: Vec3) -> f32 {
+        self.index(self.coords(position)).map_or(1.0, |i| (-self.shadow[i]).exp())
+    }
+
+    /// Unit direction of increasing exposure around `position`, or zero where it is even
+    pub fn gradient(&self, position: Vec3) -> Vec3 {
+        let h = self.cell_size;
+        Vec3::new(
+            self.exposure(position + Vec3::X * h) - self.exposure(position - Vec3::X * h),
+            self.exposure(position + Vec3::Y * h) - self.exposure(position - Vec3::Y * h),
+            self.exposure(position + Vec3::Z * h) - self.exposure(position - Vec3::Z * h),
+        )
+        .normalize_or_zero()
+    }
+
+    /// The grid as an environment for the plant at `transform`
+    pub fn view(&self, transform: GlobalTransform) -> LightView<'_> {
+        LightView { grid: self, transform }
+    }
+}
+
+/// Answers `?E(exposure, x, z)`: the bud's exposure, and optionally where light comes from
+/// along the turtle's local X (turned to by `+`) and Z (turned to by `&`), each -1..=1
+pub struct LightView<'a> {
+    grid: &'a LightGrid,
+    transform: GlobalTransform,
+}
+
+impl Environment for LightView<'_> {
+    fn respond(&self, requests: &mut [Request]) {
+        for request in requests.iter_mut().filter(|r| r.name == 'E') {
+            let position = self.transform.transform_point(request.position);
+            let frame = self.transform.rotation() * request.frame;
+            let mut answer = vec![self.grid.exposure(position)];
+            if request.params.len() >= 3 {
+                let gradient = self.grid.gradient(position);
+                answer.extend([gradient.dot(frame * Vec3::X), gradient.dot(frame * Vec3::Z)]);
+            }
+            let answered = answer.len();
+            answer.extend(request.params.iter().skip(answered));
+            request.params = answer;
+        }
+    }
+}
+
+type Moved = Or<(Changed<ShadowCasters>, Changed<GlobalTransform>)>;
+
+/// Recast the grid when any plant was redrawn or moved
+pub fn update_light_grid(
+    mut grid: ResMut<LightGrid>,
+    plants: Query<(&ShadowCasters, &GlobalTransform)>,
+    changed: Query<(), (With<Plant>, Moved)>,
+) {
+    if !changed.is_empty() {
+        grid.rebuild(plants.iter());
+    }
+}
+
+/// One synchronous growth step of a group of plants, without a `World`: every plant's current
+/// leaves are cast first, then every plant steps, those competing for light in that light.
+/// Deterministic for the same plants in the same order.
+pub fn step_competing<'a>(plants: impl IntoIterator<Item = (&'a mut Plant, GlobalTransform)>, grid: &mut LightGrid) {
+    let mut plants: Vec<_> = plants.into_iter().collect();
+    let casters: Vec<ShadowCasters> = plants.iter()
+        .map(|(plant, _)| ShadowCasters::from_skeleton(&plant.skeleton(None)))
+        .collect();
+    grid.rebuild(casters.iter().zip(plants.iter().map(|(_, transform)| transform)));
+
+    for (plant, transform) in &mut plants {
+        if plant.finished() {
+            continue;
+        }
+        if plant.environment == EnvironmentKind::Competition {
+            plant.step_in(&grid.view(*transform));
+        } else {
+            plant.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::load_preset;
+
+    #[test]
+    fn leaves_shade_what_is_below() {
+        let mut grid = LightGrid::new(Vec3::ZERO, 1.0, UVec3::splat(16));
+        grid.cast(Vec3::new(8.5, 12.5, 8.5), 4.0);
+
+        let under = grid.exposure(Vec3::new(8.5, 9.5, 8.5));
+        assert!(under < grid.exposure(Vec3::new(8.5, 14.5, 8.5)));
+        assert!(under < grid.exposure(Vec3::new(1.5, 9.5, 1.5)));
+        // Just inside the shadow's edge, light lies outwards
+        assert!(grid.gradient(Vec3::new(11.5, 9.5, 8.5)).x > 0.0);
+    }
+
+    #[test]
+    fn grid_covers_the_ecosystem() {
+        let mut settings = EcosystemSettings::default();
+        let grid = LightGrid::for_ecosystem(&settings);
+        assert_eq!((grid.origin, grid.cell_size, grid.dims), (Vec3::new(-400.0, -200.0, -400.0), 10.0, UVec3::new(80, 60, 80)));
+
+        settings.region_min = Vec2::splat(-5000.0);
+        settings.region_max = Vec2::splat(5000.0);
+        settings.terrain.base = 0.0;
+        let mut grid = LightGrid::new(Vec3::ZERO, 1.0, UVec3::ONE);
+        grid.fit_ecosystem(&settings);
+        let far = grid.origin + grid.dims.as_vec3() * grid.cell_size;
+        assert!(grid.origin.cmple(Vec3::new(-5000.0, 0.0, -5000.0)).all());
+        assert!(far.cmpge(Vec3::new(5000.0, settings.terrain.relief, 5000.0)).all());
+        assert!(grid.dims.max_element() <= MAX_CELLS as u32);
+        assert_eq!(grid.leaf_opacity, LightGrid::default().leaf_opacity);
+    }
+
+    #[test]
+    fn competition_is_deterministic() {
+        let grow = || {
+            let specimen = load_preset("Sun seeker (light competition)").unwrap();
+            let mut plants: Vec<_> = (0..3)
+                .map(|i| (specimen.to_plant().with_seed(i), GlobalTransform::from_translation(Vec3::X * 30.0 * i as f32)))
+                .collect();
+            let mut grid = LightGrid::new(Vec3::new(-100.0, -10.0, -100.0), 10.0, UVec3::new(30, 30, 20));
+            while plants.iter().any(|(plant, _)| !plant.finished()) {
+                step_competing(plants.iter_mut().map(|(plant, transform)| (plant, *transform)), &mut grid);
+            }
+            plants.into_iter().map(|(plant, _)| plant.current_string).collect::<Vec<_>>()
+        };
+
+        let first = grow();
+        assert!(first.iter().all(|string| string.contains("?E(")));
+        assert_eq!(first, grow());
+    }
+}
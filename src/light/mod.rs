@@ -0,0 +1,3 @@
+pub mod light;
+
+pub use light::{step_competing, update_light_grid, LightGrid, ShadowCasters};
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lsystem::query::QueryModule;
+
+/// Guard on a rule, e.g. `y > 40 && e < 1`, evaluated against the query modules right after
+/// the predecessor. `x y z` (and `hx hy hz`, the heading) come from `?P`, `e0 e1 …` (or just
+/// `e`) from the first other module. An empty condition always holds.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Condition {
+    source: String,
+    parsed: Option<Result<Expr, String>>,
+}
+
+impl Condition {
+    pub fn new(source: &str) -> Self {
+        let parsed = (!source.trim().is_empty()).then(|| Parser::new(source).parse());
+        Self { source: source.to_string(), parsed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parsed.is_none()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Why the condition didn't parse; a broken condition never holds
+    pub fn error(&self) -> Option<&str> {
+        match &self.parsed {
+            Some(Err(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn holds(&self, context: &[QueryModule]) -> bool {
+        match &self.parsed {
+            None => true,
+            Some(Ok(expr)) => expr.eval(context) != 0.0,
+            Some(Err(_)) => false,
+        }
+    }
+}
+
+impl PartialEq for Condition {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl From<String> for Condition {
+    fn from(source: String) -> Self {
+        Condition::new(&source)
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.source
+    }
+}
+
+#[derive(Clone)]
+enum Expr {
+    Number(f32),
+    Variable(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, &'static str, Box<Expr>),
+}
+
+impl Expr {
+    /// Booleans are 1.0 / 0.0; unknown variables are NaN, so comparisons with them fail
+    fn eval(&self, context: &[QueryModule]) -> f32 {
+        let truth = |b: bool| if b { 1.0 } else { 0.0 };
+        match self {
+            Expr::Number(n) => *n,
+            Expr::Variable(name) => lookup(name, context).unwrap_or(f32::NAN),
+            Expr::Neg(e) => -e.eval(context),
+            Expr::Not(e) => truth(e.eval(context) == 0.0),
+            Expr::Binary(a, op, b) => {
+                let (a, b) = (a.eval(context), b.eval(context));
+                match *op {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" => a / b,
+                    "<" => truth(a < b),
+                    "<=" => truth(a <= b),
+                    ">" => truth(a > b),
+                    ">=" => truth(a >= b),
+                    "==" => truth(a == b),
+                    "!=" => truth(a != b),
+                    "&&" => truth(a != 0.0 && !a.is_nan() && b != 0.0 && !b.is_nan()),
+                    "||" => truth((a != 0.0 && !a.is_nan()) || (b != 0.0 && !b.is_nan())),
+                    _ => unreachable!("parser only produces known operators"),
+                }
+            }
+        }
+    }
+}
+
+fn lookup(name: &str, context: &[QueryModule]) -> Option<f32> {
+    let position = ["x", "y", "z", "hx", "hy", "hz"].iter().position(|v| *v == name);
+    if let Some(i) = position {
+        return context.iter().find(|m| m.name == 'P')?.params.get(i).copied();
+    }
+    let index = match name.strip_prefix('e')? {
+        "" => 0,
+        digits => digits.parse().ok()?,
+    };
+    context.iter().find(|m| m.name != 'P')?.params.get(index).copied()
+}
+
+/// Recursive-descent parser; precedence from loosest: `||`, `&&`, comparisons, `+ -`, `* /`, unary
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self { rest: source }
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.binary(0)?;
+        match self.rest.trim_start() {
+            "" => Ok(expr),
+            rest => Err(format!("unexpected `{rest}`")),
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let rest = self.rest.trim_start();
+        match rest.strip_prefix(token) {
+            Some(after) => {
+                self.rest = after;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[&str]; 5] = [
+            &["||"],
+            &["&&"],
+            // Two-char operators first so `<=` isn't read as `<`
+            &["<=", ">=", "==", "!=", "<", ">"],
+            &["+", "-"],
+            &["*", "/"],
+        ];
+        let Some(ops) = LEVELS.get(level) else {
+            return self.unary();
+        };
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = ops.iter().find(|op| self.eat(op)) {
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.binary(0)?;
+            return if self.eat(")") { Ok(expr) } else { Err("missing `)`".to_string()) };
+        }
+
+        let rest = self.rest.trim_start();
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_')).unwrap_or(rest.len());
+        let (token, after) = rest.split_at(len);
+        self.rest = after;
+        match token.chars().next() {
+            None => Err("expected a value".to_string()),
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                token.parse().map(Expr::Number).map_err(|_| format!("bad number `{token}`"))
+            }
+            Some(_) => Ok(Expr::Variable(token.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::color::Color;
+
+    use super::*;
+    use crate::lsystem::{query_module_at, LSystem, Rule};
+    use crate::palette::{ColorStop, Palette};
+    use crate::plant::Plant;
+
+    /// `?P(x,y,z)` with the turtle at height `y`
+    fn at_height(y: f32) -> Vec<QueryModule> {
+        vec![QueryModule { start: 0, end: 0, name: 'P', params: vec![0.0, y, 0.0] }]
+    }
+
+    #[test]
+    fn conditions_parse_with_precedence() {
+        let holds = |source: &str| Condition::new(source).holds(&at_height(40.0));
+        assert!(holds("1 + 2 * 3 == 7"));
+        assert!(holds("(1 + 2) * 3 == 9"));
+        assert!(holds("-2 * -3 == 6"));
+        assert!(holds("0 && 0 || 1"));
+        assert!(!holds("0 && (0 || 1)"));
+        assert!(holds("1 < 2 && 3 > 2"));
+        assert!(holds("!(y > 50)"));
+
+        // `<=` is one operator, not `<` followed by `= …`
+        assert!(holds("y <= 40") && !holds("y < 40"));
+        assert!(holds("y >= 40") && !holds("y > 40"));
+
+        for broken in ["y >", "(y > 1", "y > 1)", "1..2 < y"] {
+            let condition = Condition::new(broken);
+            assert!(condition.error().is_some(), "`{broken}` should not parse");
+            assert!(!condition.holds(&at_height(40.0)));
+        }
+        assert!(Condition::new("  ").is_empty() && Condition::new("").holds(&[]));
+    }
+
+    #[test]
+    fn unknown_variables_fail_every_comparison() {
+        let holds = |source: &str| Condition::new(source).holds(&at_height(40.0));
+        assert!(!holds("w < 1") && !holds("w >= 1") && !holds("w == w"));
+        assert!(!holds("w || 0"));
+        // No environment module to read `e` from
+        assert!(!holds("e < 1") && !holds("e >= 1"));
+        assert!(holds("!(w < 1)"));
+    }
+
+    #[test]
+    fn condition_switches_rule() {
+        // A grows upwards until it is at least 25 high, then flowers
+        let mut grow = Rule::new('A', "FA");
+        grow.condition = Condition::new("y < 25");
+        let mut flower = Rule::new('A', "X");
+        flower.condition = Condition::new("y >= 25");
+        let lsystem = LSystem::new("A?P(0,0,0)", vec![grow, flower], 25.0);
+        let mut plant = Plant::new(lsystem, 10.0, 4, 1.0, Palette { stops: vec![ColorStop { position: 0.0, color: Color::WHITE }] });
+
+        let mut strings = Vec::new();
+        while !plant.finished() {
+            plant.step();
+            let chars: Vec<char> = plant.current_string.chars().collect();
+            let split = chars.iter().position(|&c| c == '?').unwrap();
+            let height = query_module_at(&chars, split).unwrap().params[1];
+            strings.push((chars[..split].iter().collect::<String>(), height));
+        }
+        let symbols: Vec<&str> = strings.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(symbols, ["FA", "FFA", "FFFA", "FFFX"]);
+        assert!((strings[3].1 - 30.0).abs() < 1e-3);
+    }
+}
@@ -1,19 +1,63 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
 use crate::lsystem::rule::Rule;
 
+/// Turn angle for one rotation axis
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct AxisAngle {
+    /// Degrees; `None` falls back to `LSystem::angle`
+    #[serde(default)]
+    pub angle: Option<f32>,
+    /// Random ± degrees added to every turn, seeded per plant
+    #[serde(default)]
+    pub jitter: f32,
+}
+
 /// Pure, stateless L-System blueprint
-#[derive (Clone)]
+#[derive (Clone, Serialize, Deserialize)]
 pub struct LSystem {
     pub axiom: String,
     pub rules: Vec<Rule>,
-    pub angle: f32
+    pub angle: f32,
+
+    /// Per-axis overrides: yaw (`\ /`), pitch (`& ^`) and roll (`+ -`)
+    #[serde(default)]
+    pub yaw: AxisAngle,
+    #[serde(default)]
+    pub pitch: AxisAngle,
+    #[serde(default)]
+    pub roll: AxisAngle,
+
+    /// Factor applied to thickness at every `!`
+    #[serde(default = "default_thickness_decay")]
+    pub thickness_decay: f32,
+
+    /// Direction branches bend towards after every `F` (e.g. gravity, -Y)
+    #[serde(default)]
+    pub tropism: Vec3,
+    /// Susceptibility to tropism; 0.0 disables bending
+    #[serde(default)]
+    pub tropism_strength: f32,
 }
 
 impl LSystem {
+    #[allow(dead_code)] // grammars are usually loaded from presets, kept for building them in code
     pub fn new(axiom: &str, rules: Vec<Rule>, angle: f32) -> Self {
         Self {
             axiom: axiom.to_string(),
             rules,
             angle,
+            thickness_decay: default_thickness_decay(),
+            yaw: AxisAngle::default(),
+            pitch: AxisAngle::default(),
+            roll: AxisAngle::default(),
+            tropism: Vec3::ZERO,
+            tropism_strength: 0.0,
         }
     }
 }
+
+fn default_thickness_decay() -> f32 {
+    0.9
+}
@@ -1,5 +1,11 @@
 pub mod lsystem;
 pub mod rule; // optional if we want Rule struct separately
+pub mod condition;
+pub mod query;
+pub mod mutation;
 
-pub use lsystem::LSystem;
+pub use lsystem::{AxisAngle, LSystem};
 pub use rule::Rule;
+pub use condition::Condition;
+pub use query::{query_module_at, right_context};
+pub use mutation::MutationRates;
@@ -0,0 +1,181 @@
+use std::collections::BTreeSet;
+
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::lsystem::{query_module_at, LSystem};
+
+/// How strongly `LSystem::mutate` changes a grammar
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MutationRates {
+    /// Chance per rule of one edit to its successor: insert, delete or replace a symbol
+    pub symbol: f32,
+    /// Largest change to the turn angle, in degrees
+    pub angle: f32,
+    /// Chance per stochastic rule of scaling its probability by 0.5–1.5
+    pub probability: f32,
+}
+
+impl Default for MutationRates {
+    fn default() -> Self {
+        Self { symbol: 0.3, angle: 5.0, probability: 0.3 }
+    }
+}
+
+impl LSystem {
+    /// A mutated copy. Successor edits only touch plain symbols, so brackets and polygon
+    /// braces stay balanced and query modules stay whole.
+    pub fn mutate(&self, rates: &MutationRates, rng: &mut StdRng) -> LSystem {
+        let mut child = self.clone();
+        let alphabet = self.alphabet();
+        if rates.angle > 0.0 {
+            child.angle = (child.angle + rng.random_range(-rates.angle..=rates.angle)).clamp(0.0, 180.0);
+        }
+        for i in 0..child.rules.len() {
+            if rng.random::<f32>() < rates.symbol {
+                child.rules[i].successor = edit_successor(&child.rules[i].successor, &alphabet, rng);
+            }
+            let predecessor = child.rules[i].predecessor;
+            let stochastic = child.rules.iter().filter(|r| r.predecessor == predecessor).count() > 1;
+            if stochastic && rng.random::<f32>() < rates.probability {
+                child.rules[i].probability = (child.rules[i].probability * rng.random_range(0.5..=1.5)).max(0.01);
+            }
+        }
+        child
+    }
+
+    /// Uniform crossover of the rule sets: each predecessor's rules, all stochastic
+    /// alternatives together, come from one parent or the other. The angle is drawn from
+    /// either parent too; everything else is `self`'s.
+    pub fn crossover(&self, other: &LSystem, rng: &mut StdRng) -> LSystem {
+        let mut child = self.clone();
+        let mut predecessors = Vec::new();
+        for rule in self.rules.iter().chain(&other.rules) {
+            if !predecessors.contains(&rule.predecessor) {
+                predecessors.push(rule.predecessor);
+            }
+        }
+        child.rules.clear();
+        for predecessor in predecessors {
+            let parent = if rng.random() { self } else { other };
+            child.rules.extend(parent.rules.iter().filter(|r| r.predecessor == predecessor).cloned());
+        }
+        if rng.random() {
+            child.angle = other.angle;
+        }
+        child
+    }
+
+    /// Plain symbols the grammar uses, the pool mutations draw from
+    fn alphabet(&self) -> Vec<char> {
+        let strings = std::iter::once(&self.axiom).chain(self.rules.iter().map(|r| &r.successor));
+        let symbols: BTreeSet<char> = strings
+            .flat_map(|s| tokens(s))
+            .filter(|t| is_plain(t))
+            .filter_map(|t| t.chars().next())
+            .chain(self.rules.iter().map(|r| r.predecessor))
+            .collect();
+        symbols.into_iter().collect()
+    }
+}
+
+/// Split a string into query modules, kept whole, and single chars
+fn tokens(string: &str) -> Vec<String> {
+    let chars: Vec<char> = string.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let end = query_module_at(&chars, i).map_or(i + 1, |m| m.end);
+        tokens.push(chars[i..end].iter().collect());
+        i = end;
+    }
+    tokens
+}
+
+/// A symbol that can be removed or replaced without unbalancing anything
+fn is_plain(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if !"[]{}?".contains(c))
+}
+
+/// One random edit: insert a symbol (sometimes as a new branch), delete one or replace one
+fn edit_successor(successor: &str, alphabet: &[char], rng: &mut StdRng) -> String {
+    let mut tokens = tokens(successor);
+    let plain: Vec<usize> = (0..tokens.len()).filter(|&i| is_plain(&tokens[i])).collect();
+    match rng.random_range(0..3) {
+        0 => {
+            if let Some(&symbol) = alphabet.choose(rng) {
+                // Not between a symbol and its query modules
+                let slots: Vec<usize> = (0..=tokens.len())
+                    .filter(|&i| tokens.get(i).is_none_or(|t| !t.starts_with('?')))
+                    .collect();
+                let at = slots[rng.random_range(0..slots.len())];
+                let token = if rng.random::<f32>() < 0.25 { format!("[{symbol}]") } else { symbol.to_string() };
+                tokens.insert(at, token);
+            }
+        }
+        1 => {
+            if let Some(&i) = plain.choose(rng) {
+                tokens.remove(i);
+            }
+        }
+        _ => {
+            if let (Some(&i), Some(&symbol)) = (plain.choose(rng), alphabet.choose(rng)) {
+                tokens[i] = symbol.to_string();
+            }
+        }
+    }
+
+    let mut out = tokens.concat();
+    // Deleting can leave empty branches behind
+    while out.contains("[]") || out.contains("{}") {
+        out = out.replace("[]", "").replace("{}", "");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::load_preset;
+
+    fn balanced(string: &str) -> bool {
+        let mut depths = [0i32; 2];
+        for c in string.chars() {
+            match c {
+                '[' => depths[0] += 1,
+                ']' => depths[0] -= 1,
+                '{' => depths[1] += 1,
+                '}' => depths[1] -= 1,
+                _ => {}
+            }
+            if depths.iter().any(|&d| d < 0) {
+                return false;
+            }
+        }
+        depths == [0, 0]
+    }
+
+    #[test]
+    fn mutants_stay_balanced_and_reproducible() {
+        let flower = load_preset("Flower").unwrap().lsystem;
+        let topiary = load_preset("Topiary (open L-system)").unwrap().lsystem;
+        let rates = MutationRates { symbol: 1.0, ..MutationRates::default() };
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut lsystem = flower.crossover(&topiary, &mut rng);
+        for _ in 0..200 {
+            lsystem = lsystem.mutate(&rates, &mut rng);
+            for rule in &lsystem.rules {
+                assert!(balanced(&rule.successor), "unbalanced: {}", rule.successor);
+                let chars: Vec<char> = rule.successor.chars().collect();
+                assert!(chars.iter().enumerate().all(|(i, &c)| c != '?' || query_module_at(&chars, i).is_some()));
+            }
+        }
+
+        let mutant = |seed| flower.mutate(&rates, &mut StdRng::seed_from_u64(seed));
+        assert!(mutant(9).rules == mutant(9).rules && mutant(9).angle == mutant(9).angle);
+        assert!(mutant(9).rules != flower.rules);
+    }
+}
@@ -0,0 +1,98 @@
+/// A query (`?P(x,y,z)`) or communication (`?E(a,b,…)`) module in an L-system string.
+/// Their parameters are filled in between rewriting steps, never by rules.
+#[derive(Clone)]
+pub struct QueryModule {
+    /// Char index of the `?`
+    pub start: usize,
+    /// Char index just past the module
+    pub end: usize,
+    /// `P` for the turtle position, anything else goes to the environment
+    pub name: char,
+    pub params: Vec<f32>,
+}
+
+impl QueryModule {
+    /// Append the module's text, parameters rounded to two decimals to keep strings short
+    pub fn write(&self, out: &mut String) {
+        out.push('?');
+        out.push(self.name);
+        out.push('(');
+        for (i, p) in self.params.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let rounded = (p * 100.0).round() / 100.0;
+            // Avoid printing "-0"
+            out.push_str(&(rounded + 0.0).to_string());
+        }
+        out.push(')');
+    }
+}
+
+/// Parse the module starting at `chars[start]`, if there is one: `?`, a letter, and an
+/// optional parenthesised list of numbers
+pub fn query_module_at(chars: &[char], start: usize) -> Option<QueryModule> {
+    if chars.get(start) != Some(&'?') {
+        return None;
+    }
+    let name = *chars.get(start + 1).filter(|c| c.is_ascii_alphabetic())?;
+    if chars.get(start + 2) != Some(&'(') {
+        return Some(QueryModule { start, end: start + 2, name, params: Vec::new() });
+    }
+
+    let close = start + 3 + chars[start + 3..].iter().position(|&c| c == ')')?;
+    let inner: String = chars[start + 3..close].iter().collect();
+    let params = inner.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse().unwrap_or(0.0))
+        .collect();
+    Some(QueryModule { start, end: close + 1, name, params })
+}
+
+/// The run of query modules directly after `chars[index]`, its right context
+pub fn right_context(chars: &[char], index: usize) -> Vec<QueryModule> {
+    let mut modules = Vec::new();
+    let mut at = index + 1;
+    while let Some(module) = query_module_at(chars, at) {
+        at = module.end;
+        modules.push(module);
+    }
+    modules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(string: &str, start: usize) -> Option<QueryModule> {
+        query_module_at(&string.chars().collect::<Vec<_>>(), start)
+    }
+
+    #[test]
+    fn modules_round_trip_through_text() {
+        let module = parse("F?P(1.5, -2,0.004)[", 1).unwrap();
+        assert_eq!((module.start, module.end, module.name), (1, 18, 'P'));
+        assert_eq!(module.params, [1.5, -2.0, 0.004]);
+
+        let mut text = String::new();
+        module.write(&mut text);
+        assert_eq!(text, "?P(1.5,-2,0)");
+        let again = parse(&text, 0).unwrap();
+        assert_eq!((again.end, again.name), (text.chars().count(), 'P'));
+        assert_eq!(again.params, [1.5, -2.0, 0.0]);
+
+        let bare = parse("A?E", 1).unwrap();
+        assert_eq!((bare.end, bare.name, bare.params.len()), (3, 'E', 0));
+        let mut text = String::new();
+        bare.write(&mut text);
+        assert_eq!(text, "?E()");
+        assert_eq!(parse(&text, 0).unwrap().params.len(), 0);
+
+        assert!(parse("?P(1,2", 0).is_none());
+        assert!(parse("?1", 0).is_none());
+        assert!(parse("F?P", 0).is_none());
+        let chars: Vec<char> = "A?P(1)?E(2,3)F".chars().collect();
+        assert_eq!(right_context(&chars, 0).iter().map(|m| m.name).collect::<String>(), "PE");
+    }
+}
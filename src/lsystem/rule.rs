@@ -1,9 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lsystem::condition::Condition;
+
 /// A rule in an L-System
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Rule {
     pub predecessor: char,      // For now, single char
     pub successor: String,      // Replacement string
+    #[serde(default = "default_probability")]
     pub probability: f32,       // 0.0..1.0
+    /// Only applies while this holds for the query modules after the predecessor
+    #[serde(default, skip_serializing_if = "Condition::is_empty")]
+    pub condition: Condition,
 }
 
 impl Rule {
@@ -12,14 +20,21 @@ impl Rule {
             predecessor,
             successor: successor.to_string(),
             probability: 1.0,
+            condition: Condition::default(),
         }
     }
 
+    #[allow(dead_code)] // see `LSystem::new`
     pub fn with_probability(predecessor: char, successor: &str, probability: f32) -> Self {
         Self {
             predecessor,
             successor: successor.to_string(),
             probability,
+            condition: Condition::default(),
         }
     }
 }
+
+fn default_probability() -> f32 {
+    1.0
+}
@@ -1,4 +1,7 @@
-use bevy::{input::common_conditions::input_just_pressed, math::VectorSpace, prelude::*};
+// Each module keeps its code in a same-named file (e.g. `plant/plant.rs`)
+#![allow(clippy::module_inception)]
+
+use bevy::prelude::*;
 use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
 
 // Our modules
@@ -7,21 +10,81 @@ mod plant;
 mod draw;
 mod ui;
 mod input;
-
-use lsystem::{LSystem, Rule};
-use plant::Plant;
-use draw::draw_plant;
-use ui::{plant_ui, palette_ui};
+mod specimen;
+mod presets;
+mod variation;
+mod palette;
+mod material;
+mod impostor;
+mod wind;
+mod skeleton;
+mod export;
+mod stats;
+mod environment;
+mod light;
+mod colonization;
+mod field;
+mod ecosystem;
+mod terrain;
+mod simulation;
+mod evolution;
+
+use plant::{plant_step_system, GrowthClock, SelectedPlant};
+use draw::{billboard_face_camera, draw_plant};
+use impostor::finish_impostor_bakes;
+use ui::{plant_ui, palette_ui, specimen_ui, variation_ui, material_ui, wind_ui, stats_ui, string_viewer_ui, growth_ui, ecosystem_ui, simulation_ui, breeding_ui, SpecimenFile, StringViewer};
+use wind::WindPlugin;
+use light::{update_light_grid, LightGrid};
+use colonization::{load_volume_meshes, VolumeMeshes};
+use ecosystem::{respawn_ecosystem, spawn_ecosystem, EcosystemSettings};
+use simulation::{run_headless, run_simulation, SimulationSettings};
+use evolution::{run_genetic, spawn_brood, Breeding, GeneticSettings};
 use input::{CameraController, InputPlugin};
 
 fn main() {
+    // `--simulate <generations> [--seed <world seed>]` runs the ecosystem simulation without a
+    // window and prints the population of each species per generation
+    let args: Vec<String> = std::env::args().collect();
+    let value = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1));
+    if args.iter().any(|a| a == "--simulate" || a == "--evolve") {
+        // Without an app there's no LogPlugin, so warnings need a subscriber of their own
+        bevy::log::tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    }
+    if args.iter().any(|a| a == "--simulate") {
+        let generations = value("--simulate").and_then(|n| n.parse().ok()).unwrap_or(100);
+        run_headless(generations, value("--seed").and_then(|s| s.parse().ok()));
+        return;
+    }
+    // `--evolve [settings.ron]` optimises a grammar against fitness objectives, see `GeneticSettings`
+    if args.iter().any(|a| a == "--evolve") {
+        let settings = match value("--evolve").filter(|path| !path.starts_with("--")) {
+            Some(path) => GeneticSettings::load(std::path::Path::new(path)),
+            None => Ok(GeneticSettings::default()),
+        };
+        if let Err(e) = settings.and_then(run_genetic) {
+            eprintln!("evolution failed: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
-        .add_plugins((DefaultPlugins, InputPlugin))
+        .add_plugins((DefaultPlugins, InputPlugin, WindPlugin))
         .add_plugins(EguiPlugin::default())
         .insert_resource(ClearColor(Color::srgb(0.1, 0.1, 0.1)))
-        .add_systems(Startup, (setup_camera, setup_lighting, spawn_flowers))
-        .add_systems(Update, (draw_plant, plant_step_system))
-        .add_systems(EguiPrimaryContextPass, (plant_ui, palette_ui))
+        .init_resource::<SelectedPlant>()
+        .init_resource::<SpecimenFile>()
+        .init_resource::<StringViewer>()
+        .init_resource::<GrowthClock>()
+        .init_resource::<LightGrid>()
+        .init_resource::<VolumeMeshes>()
+        .init_resource::<EcosystemSettings>()
+        .init_resource::<SimulationSettings>()
+        .init_resource::<Breeding>()
+        .add_systems(Startup, (setup_camera, setup_lighting, spawn_ecosystem))
+        .add_systems(FixedUpdate, plant_step_system)
+        .add_systems(Update, (respawn_ecosystem, run_simulation.after(respawn_ecosystem), spawn_brood, load_volume_meshes.before(draw_plant), draw_plant, update_light_grid.after(draw_plant), finish_impostor_bakes.after(draw_plant), billboard_face_camera))
+        .add_systems(EguiPrimaryContextPass, (plant_ui, palette_ui, specimen_ui, variation_ui, material_ui, wind_ui, stats_ui, string_viewer_ui, growth_ui, ecosystem_ui, simulation_ui, breeding_ui))
         .run();
 }
 
@@ -96,107 +159,3 @@ fn setup_lighting(mut commands: Commands) {
         )),
     ));
 }
-
-fn spawn_flowers(mut commands: Commands) {
-    let lsystem = LSystem::new(
-        "P", // axiom
-        vec![
-            Rule::new('P', "N+[P+O]--//[--L]N[++L]-[PO]++PO"),
-            Rule::new('N', "FS[//&&L][//^^L]FS"),
-            Rule::with_probability('S', "S[//&&L][//^^L]FS", 0.33),
-            Rule::with_probability('S', "SFS", 0.33),
-            Rule::with_probability('S', "S", 0.33),
-            Rule::new('L', "['{+f-f-f+|+f-f-f}]"),
-            Rule::new('O', "[&&&D'/W////W////W////W////W]"),
-            Rule::new('D', "FF"),
-            Rule::new('W', "['^^^F][{&&&&-f+f|-f+f}]"),
-        ],
-        18.0,
-    );
-
-    let grid_size = 4;
-    let spacing = 150.0; // adjust this to control distance between flowers
-
-    for x in 0..grid_size {
-        for z in 0..grid_size {
-            let x_pos = (x as f32 - (grid_size as f32 - 1.0) / 2.0) * spacing;
-            let z_pos = (z as f32 - (grid_size as f32 - 1.0) / 2.0) * spacing;
-
-            commands.spawn((
-                Plant::new(
-                    lsystem.clone(), // clone so each flower gets its own LSystem
-                    5.0,
-                    5,
-                    1.0,
-                    vec![
-                        Color::srgb(0.2, 0.7, 0.3),
-                        Color::srgb(0.3, 0.8, 0.7),
-                        Color::srgb(0.9, 0.0, 0.10),
-                    ],
-                ),
-                Transform::from_translation(Vec3::new(x_pos, -200.0, z_pos)),
-                GlobalTransform::default(),
-                Visibility::default(),
-            ));
-        }
-    }
-}
-
-
-// Example plant
-fn spawn_example_plant(mut commands: Commands) {
-    // let lsystem = LSystem::new(
-    //     "X",
-    //     vec![
-    //         Rule::new('F', "FF"),
-    //         Rule::new('X', "F-[[X]+X]+F[+FX]-X"),
-    //     ],
-    //     22.5,
-    // );
-    let lsystem = LSystem::new(
-    "A", // axiom
-    vec![
-        Rule::new('A', "[&FL!A]/////'[&FL!A]///////'[&FL!A]"),
-        Rule::new('F', "S/////F"),
-        Rule::new('S', "FL"),
-        Rule::new('L', "['''^^{-f+f+f-|-f+f+f}]"),
-    ],
-    22.5, // delta in degrees
-    );
-
-    commands.spawn((
-        Plant::new(
-            lsystem,
-            10.0,
-            7,
-            5.0,
-            vec![
-            Color::srgba(80.0 / 255.0, 42.0 / 255.0, 20.0 / 255.0, 1.0),
-            // Stem mid – medium brown
-            Color::srgba(121.0 / 255.0, 76.0 / 255.0, 39.0 / 255.0, 1.0),
-            // Stem tip / small branches – lighter reddish-brown
-            Color::srgba(160.0 / 255.0, 96.0 / 255.0, 40.0 / 255.0, 1.0),
-            // Leaf 1 – dark green
-            Color::srgba(34.0 / 255.0, 139.0 / 255.0, 34.0 / 255.0, 1.0),
-            // Leaf 2 – medium green
-            Color::srgba(50.0 / 255.0, 205.0 / 255.0, 50.0 / 255.0, 1.0),
-            // Leaf 3 – bright green
-            Color::srgba(124.0 / 255.0, 252.0 / 255.0, 0.0 / 255.0, 1.0),
-                // Color::srgb(0.90, 0.75, 0.50), // yellowish highlight
-                // Color::srgb(0.85, 0.45, 0.55), // optional petal tint
-            ]),
-        Transform::from_translation(Vec3::new(0.0,-200.0,0.0)),
-        GlobalTransform::default(),
-        Visibility::default(),
-    ));
-}
-
-
-// Step system
-fn plant_step_system(mut query: Query<&mut Plant>) {
-    for mut plant in query.iter_mut() {
-        if !plant.finished() {
-            plant.step();
-        }
-    }
-}
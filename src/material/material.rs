@@ -0,0 +1,97 @@
+use bevy::{prelude::*, render::render_resource::Face};
+use serde::{Deserialize, Serialize};
+
+use crate::palette::hex_color;
+
+/// Which material a leaf polygon (`{ ... }`) is drawn with
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum SurfaceKind {
+    #[default]
+    Leaf,
+    /// Polygons drawn after `%` in the current branch
+    Petal,
+}
+
+/// PBR parameters for one part of the plant; vertex colours are multiplied by `base_color`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SurfaceMaterial {
+    #[serde(with = "hex_color")]
+    pub base_color: Color,
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+    pub reflectance: f32,
+    /// Render back faces too (with flipped normals) instead of duplicating geometry
+    pub double_sided: bool,
+    /// Light passing diffusely through thin surfaces, 0..=1
+    #[serde(default)]
+    pub diffuse_transmission: f32,
+    /// Alpha-blend using the palette alpha
+    #[serde(default)]
+    pub alpha_blend: bool,
+}
+
+impl SurfaceMaterial {
+    pub fn to_standard_material(&self) -> StandardMaterial {
+        StandardMaterial {
+            base_color: self.base_color,
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: self.metallic,
+            reflectance: self.reflectance,
+            double_sided: self.double_sided,
+            cull_mode: if self.double_sided { None } else { Some(Face::Back) },
+            diffuse_transmission: self.diffuse_transmission,
+            alpha_mode: if self.alpha_blend { AlphaMode::Blend } else { AlphaMode::Opaque },
+            ..default()
+        }
+    }
+}
+
+/// Separate materials for branches, leaves and petals
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlantMaterials {
+    pub bark: SurfaceMaterial,
+    pub leaf: SurfaceMaterial,
+    pub petal: SurfaceMaterial,
+    /// Bark texture repeats per world unit along a branch
+    #[serde(default = "default_texel_density")]
+    pub texel_density: f32,
+}
+
+impl Default for PlantMaterials {
+    fn default() -> Self {
+        Self {
+            bark: SurfaceMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 0.9,
+                metallic: 0.0,
+                reflectance: 0.2,
+                double_sided: false,
+                diffuse_transmission: 0.0,
+                alpha_blend: false,
+            },
+            leaf: SurfaceMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 0.6,
+                metallic: 0.0,
+                reflectance: 0.3,
+                double_sided: true,
+                diffuse_transmission: 0.3, // thin leaves glow when backlit
+                alpha_blend: false,
+            },
+            petal: SurfaceMaterial {
+                base_color: Color::WHITE,
+                perceptual_roughness: 0.5,
+                metallic: 0.0,
+                reflectance: 0.3,
+                double_sided: true,
+                diffuse_transmission: 0.4,
+                alpha_blend: true,
+            },
+            texel_density: default_texel_density(),
+        }
+    }
+}
+
+fn default_texel_density() -> f32 {
+    0.1
+}
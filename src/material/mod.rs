@@ -0,0 +1,3 @@
+pub mod material;
+
+pub use material::{PlantMaterials, SurfaceKind, SurfaceMaterial};
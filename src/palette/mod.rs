@@ -0,0 +1,3 @@
+pub mod palette;
+
+pub use palette::{hex_color, ColorMode, ColorStop, Palette};
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// What drives the colour of each segment and leaf
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// Palette entry picked by the `'` counter
+    #[default]
+    Index,
+    /// Gradient over branch (stack) depth
+    Depth,
+    /// Gradient over the iteration in which the symbol was created
+    Iteration,
+    /// Gradient over path length from the root
+    Distance,
+}
+
+/// One colour on the gradient, `position` in 0..=1
+#[derive(Clone, Copy)]
+pub struct ColorStop {
+    pub position: f32,
+    pub color: Color,
+}
+
+/// Colour stops used both as an indexed palette and as an Oklab gradient
+#[derive(Clone)]
+pub struct Palette {
+    pub stops: Vec<ColorStop>,
+}
+
+impl Palette {
+    /// Palette entry for the `'` counter, wrapping around
+    pub fn index(&self, i: usize) -> Color {
+        self.stops[i % self.stops.len()].color
+    }
+
+    /// Interpolate in Oklab at `t` (clamped to 0..=1); stops need not be sorted
+    pub fn sample(&self, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let below = self.stops.iter()
+            .filter(|s| s.position <= t)
+            .max_by(|a, b| a.position.total_cmp(&b.position));
+        let above = self.stops.iter()
+            .filter(|s| s.position >= t)
+            .min_by(|a, b| a.position.total_cmp(&b.position));
+
+        match (below, above) {
+            (Some(a), Some(b)) if b.position > a.position => {
+                let f = (t - a.position) / (b.position - a.position);
+                Oklaba::from(a.color).mix(&Oklaba::from(b.color), f).into()
+            }
+            (Some(s), _) | (None, Some(s)) => s.color,
+            (None, None) => Color::WHITE,
+        }
+    }
+}
+
+/// Stored on disk as sRGB hex; a bare "#RRGGBB" entry (older files) is spaced evenly
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StopRepr {
+    Hex(String),
+    Stop { position: f32, color: String },
+}
+
+fn parse_hex<E: Error>(hex: &str) -> Result<Color, E> {
+    Srgba::hex(hex)
+        .map(Color::from)
+        .map_err(|e| E::custom(format!("bad colour {hex:?}: {e}")))
+}
+
+/// `#[serde(with = "hex_color")]` for a single colour stored as sRGB hex
+pub mod hex_color {
+    use bevy::prelude::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&color.to_srgba().to_hex())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        super::parse_hex(&String::deserialize(deserializer)?)
+    }
+}
+
+impl Serialize for Palette {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.stops.iter().map(|s| StopRepr::Stop {
+            position: s.position,
+            color: s.color.to_srgba().to_hex(),
+        }))
+    }
+}
+
+impl<'de> Deserialize<'de> for Palette {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let reprs = Vec::<StopRepr>::deserialize(deserializer)?;
+        let last = reprs.len().saturating_sub(1).max(1) as f32;
+        let stops = reprs
+            .iter()
+            .enumerate()
+            .map(|(i, repr)| match repr {
+                StopRepr::Hex(hex) => Ok(ColorStop { position: i as f32 / last, color: parse_hex(hex)? }),
+                StopRepr::Stop { position, color } => Ok(ColorStop { position: *position, color: parse_hex(color)? }),
+            })
+            .collect::<Result<Vec<_>, D::Error>>()?;
+
+        if stops.is_empty() {
+            return Err(D::Error::custom("palette needs at least one colour"));
+        }
+        Ok(Self { stops })
+    }
+}
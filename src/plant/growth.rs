@@ -0,0 +1,80 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+
+use crate::environment::EnvironmentKind;
+use crate::light::LightGrid;
+use crate::plant::Plant;
+use crate::stats::PlantStats;
+
+/// Global growth playback, applied on the fixed timestep so speed doesn't depend on frame rate
+#[derive(Resource)]
+pub struct GrowthClock {
+    pub playing: bool,
+    /// Multiplier on `rate`
+    pub speed: f32,
+    /// Iterations per second at speed 1
+    pub rate: f32,
+    /// Step every plant once on the next tick, even when paused
+    pub step_once: bool,
+}
+
+impl Default for GrowthClock {
+    fn default() -> Self {
+        Self { playing: true, speed: 1.0, rate: 2.0, step_once: false }
+    }
+}
+
+/// Per-plant growth timing, kept off `Plant` so ticking it doesn't trigger a redraw
+#[derive(Component, Default)]
+pub struct GrowthTimer {
+    /// Seconds of growth time before the first step, to stagger a field of plants
+    pub start_delay: f32,
+    elapsed: f32,
+    /// Fraction of the next step already earned
+    due: f32,
+}
+
+impl GrowthTimer {
+    pub fn delayed(start_delay: f32) -> Self {
+        Self { start_delay, ..default() }
+    }
+}
+
+pub fn plant_step_system(
+    time: Res<Time>,
+    mut clock: ResMut<GrowthClock>,
+    grid: Res<LightGrid>,
+    mut query: Query<(&mut Plant, &mut GrowthTimer, &mut PlantStats, &GlobalTransform)>,
+) {
+    let dt = if clock.playing { time.delta_secs() * clock.speed } else { 0.0 };
+    let step_once = std::mem::take(&mut clock.step_once);
+
+    for (mut plant, mut timer, mut stats, transform) in &mut query {
+        let mut steps = usize::from(step_once);
+        if plant.playing && !plant.finished() {
+            timer.elapsed += dt;
+            if timer.elapsed >= timer.start_delay {
+                timer.due += dt * clock.rate;
+                steps += timer.due as usize;
+                timer.due = timer.due.fract();
+            }
+        } else {
+            // Don't bank steps while paused or finished
+            timer.due = 0.0;
+        }
+
+        for _ in 0..steps {
+            if plant.finished() {
+                break;
+            }
+            let start = Instant::now();
+            if plant.environment == EnvironmentKind::Competition {
+                plant.step_in(&grid.view(*transform));
+            } else {
+                plant.step();
+            }
+            stats.step_time = start.elapsed();
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod plant;
+pub mod growth;
 
-pub use plant::Plant;
+pub use plant::{Plant, SelectedPlant};
+pub use growth::{plant_step_system, GrowthClock, GrowthTimer};
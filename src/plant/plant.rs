@@ -1,58 +1,206 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
 use bevy::prelude::*;
 use rand::prelude::*;
-use crate::lsystem::{LSystem,Rule};
+use rand::rngs::StdRng;
+use crate::lsystem::{query_module_at, right_context, LSystem, Rule};
+use crate::variation::VariationProfile;
+use crate::draw::{LodSettings, ThicknessModel};
+use crate::palette::{ColorMode, Palette};
+use crate::material::PlantMaterials;
+use crate::skeleton::RigSettings;
+use crate::stats::PlantStats;
+use crate::plant::GrowthTimer;
+use crate::light::ShadowCasters;
+use crate::environment::{Environment, EnvironmentKind, Request};
+use crate::draw::draw::interpret_plant;
+use crate::draw::thickness::apply_pipe_model;
+use crate::colonization::{colonize, Generator};
+use crate::skeleton::PlantSkeleton;
+
+/// The plant currently being edited in the UI
+#[derive(Resource, Default)]
+pub struct SelectedPlant(pub Option<Entity>);
 
 /// ECS component representing an individual plant
-#[derive(Component)]
+#[derive(Component, Clone)]
+#[require(PlantStats, GrowthTimer, ShadowCasters)]
 pub struct Plant {
     /// Reference to the stateless L-System blueprint
     pub lsystem: LSystem,
 
     /// Current developmental state
     pub current_string: String,
+    /// Iteration in which each char of `current_string` was created
+    pub birth: Vec<u16>,
     pub iteration: usize,
     pub max_iterations: usize,
+    /// String and births of every earlier iteration from `history_start` on
+    history: Vec<(String, Vec<u16>)>,
+    /// Iteration of `history[0]`; later than 0 once the rules changed mid-growth, since the
+    /// strings before it no longer follow from them
+    history_start: usize,
+    /// Whether `plant_step_system` advances this plant (on top of `GrowthClock::playing`)
+    pub playing: bool,
 
     /// Per-plant growth/drawing parameters
     pub step_size: f32,
     pub root_thickness:f32,
-    pub palette: Vec<Color>
+    pub thickness: ThicknessModel,
+    pub palette: Palette,
+    pub color_mode: ColorMode,
+    pub materials: PlantMaterials,
+    pub lod: LodSettings,
+    pub rig: RigSettings,
+    /// Geometric noise applied while drawing
+    pub variation: VariationProfile,
+    /// Answers the string's communication modules (`?E`) between steps
+    pub environment: EnvironmentKind,
+    /// L-system interpretation or space colonization
+    pub generator: Generator,
+
+    /// Seed for stochastic rule selection; the same seed always grows the same plant
+    pub seed: u64,
 }
 
 impl Plant {
     /// Create a new plant from a given LSystem blueprint
-    pub fn new(lsystem: LSystem, step_size: f32, max_iterations: usize, root_thickness: f32, palette: Vec<Color>) -> Self {
+    pub fn new(lsystem: LSystem, step_size: f32, max_iterations: usize, root_thickness: f32, palette: Palette) -> Self {
         let axiom = lsystem.axiom.clone();
         Self {
             lsystem,
+            birth: vec![0; axiom.chars().count()],
             current_string: axiom,
             iteration: 0,
             max_iterations,
+            history: Vec::new(),
+            history_start: 0,
+            playing: true,
             step_size,
             root_thickness,
+            thickness: ThicknessModel::default(),
             palette,
+            color_mode: ColorMode::default(),
+            materials: PlantMaterials::default(),
+            lod: LodSettings::default(),
+            rig: RigSettings::default(),
+            variation: VariationProfile::default(),
+            environment: EnvironmentKind::default(),
+            generator: Generator::default(),
+            seed: rand::random(),
         }
     }
 
+    /// Replace the random seed so growth is reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Take over `other`'s growth state (string, births, iteration and history), keeping
+    /// this plant's own drawing settings
+    pub fn copy_growth(&mut self, other: &Plant) {
+        self.current_string.clone_from(&other.current_string);
+        self.birth.clone_from(&other.birth);
+        self.iteration = other.iteration;
+        self.history.clone_from(&other.history);
+        self.history_start = other.history_start;
+    }
+
     /// Reset the plant to its initial state
     pub fn reset(&mut self) {
         self.current_string = self.lsystem.axiom.clone();
+        self.birth = vec![0; self.current_string.chars().count()];
         self.iteration = 0;
+        self.history.clear();
+        self.history_start = 0;
+    }
+
+    /// Drop the recorded steps, once they no longer follow from what `step` reads
+    pub fn forget_history(&mut self) {
+        self.history.clear();
+        self.history_start = self.iteration;
+    }
+
+    /// Hash of everything `step` reads: the grammar, seed, environment and generator, and the
+    /// turtle geometry query modules report
+    pub fn growth_fingerprint(&self) -> u64 {
+        let inputs = (&self.lsystem, self.seed, &self.environment, &self.generator, self.step_size, &self.variation);
+        let mut hasher = DefaultHasher::new();
+        ron::to_string(&inputs).unwrap_or_default().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Go back one iteration, restoring the string exactly as it was; false with no history left
+    pub fn step_back(&mut self) -> bool {
+        let Some((string, birth)) = self.history.pop() else { return false };
+        self.current_string = string;
+        self.birth = birth;
+        self.iteration -= 1;
+        true
+    }
+
+    /// Step back or forward until the plant is at `iteration` (clamped to `max_iterations`).
+    /// Iterations before the history regrow from the axiom, which stepping's seed makes exact.
+    pub fn go_to(&mut self, iteration: usize) {
+        let iteration = iteration.min(self.max_iterations);
+        if iteration < self.history_start {
+            self.reset();
+        }
+        while self.iteration > iteration && self.step_back() {}
+        while self.iteration < iteration {
+            self.step();
+        }
     }
 
-    /// Advance the plant one step using its LSystem rules
+    /// Advance the plant one step using its LSystem rules, in its own environment
     pub fn step(&mut self) {
+        let environment = self.environment.clone();
+        self.step_in(&environment);
+    }
+
+    /// Advance one step in `environment`. Query modules are answered before rewriting, so
+    /// conditions see the current geometry, and again after so the string shows fresh values.
+    pub fn step_in(&mut self, environment: &dyn Environment) {
+        // Colonized plants regrow from the iteration count alone
+        if self.generator != Generator::LSystem {
+            self.history.push((self.current_string.clone(), self.birth.clone()));
+            self.iteration += 1;
+            return;
+        }
+        self.answer_queries(environment);
+
         let mut next = String::new();
-        let mut rng = rand::rng();
+        let mut next_birth = Vec::new();
+        let born = (self.iteration + 1).min(u16::MAX as usize) as u16;
+        // Derive each iteration's RNG from the seed so stepping is deterministic
+        let mut rng = StdRng::seed_from_u64(
+            self.seed ^ (self.iteration as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+        );
+
+        let chars: Vec<char> = self.current_string.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let (c, birth) = (chars[i], self.birth[i]);
+
+            // Query modules are carried over whole; only the environment changes them
+            if let Some(module) = query_module_at(&chars, i) {
+                next.extend(&chars[i..module.end]);
+                next_birth.extend_from_slice(&self.birth[i..module.end]);
+                i = module.end;
+                continue;
+            }
+            i += 1;
 
-        for c in self.current_string.chars() {
             // collect all matching rules
+            let context = if chars.get(i) == Some(&'?') { right_context(&chars, i - 1) } else { Vec::new() };
             let matches: Vec<&Rule> = self.lsystem.rules.iter()
-                .filter(|r| r.predecessor == c)
+                .filter(|r| r.predecessor == c && r.condition.holds(&context))
                 .collect();
 
             if matches.is_empty() {
                 next.push(c);
+                next_birth.push(birth);
             } else {
                 let total_prob: f32 = matches.iter().map(|r| r.probability).sum();
                 let mut roll = rng.random::<f32>() * total_prob; // scale 0..1 to 0..total_prob
@@ -69,14 +217,122 @@ impl Plant {
 
                 if let Some(rule) = picked {
                     next.push_str(&rule.successor);
+                    next_birth.extend(std::iter::repeat_n(born, rule.successor.chars().count()));
                 } else {
                     next.push(c);
+                    next_birth.push(birth);
                 }
             }
         }
 
-        self.current_string = next;
+        if next.contains('~') {
+            (next, next_birth) = cut_branches(&next, &next_birth);
+        }
+
+        let previous = std::mem::replace(&mut self.current_string, next);
+        let previous_birth = std::mem::replace(&mut self.birth, next_birth);
+        self.history.push((previous, previous_birth));
         self.iteration += 1;
+
+        self.answer_queries(environment);
+    }
+
+    /// Fill `?P` modules with the turtle position (and heading, given six parameters) and
+    /// let `environment` answer the rest
+    fn answer_queries(&mut self, environment: &dyn Environment) {
+        if !self.current_string.contains('?') {
+            return;
+        }
+        let skeleton = interpret_plant(&self.current_string, &self.birth, self);
+        let chars: Vec<char> = self.current_string.chars().collect();
+
+        let mut modules = Vec::new();
+        let mut requests = Vec::new();
+        for point in &skeleton.queries {
+            let Some(mut module) = query_module_at(&chars, point.symbol) else { continue };
+            if module.name == 'P' {
+                let heading = point.frame * Vec3::Y;
+                let mut params = vec![point.position.x, point.position.y, point.position.z];
+                if module.params.len() >= 6 {
+                    params.extend([heading.x, heading.y, heading.z]);
+                }
+                module.params = params;
+            } else {
+                requests.push(Request { name: module.name, position: point.position, frame: point.frame, params: module.params.clone() });
+            }
+            modules.push(module);
+        }
+        environment.respond(&mut requests);
+
+        let mut answers = requests.into_iter();
+        let mut next = String::new();
+        let mut next_birth = Vec::new();
+        let mut copied = 0;
+        for mut module in modules {
+            if module.name != 'P' {
+                module.params = answers.next().map_or(module.params, |r| r.params);
+            }
+            next.extend(&chars[copied..module.start]);
+            next_birth.extend_from_slice(&self.birth[copied..module.start]);
+            // Module text is ASCII, so bytes written are chars written
+            let len = next.len();
+            module.write(&mut next);
+            next_birth.extend(std::iter::repeat_n(self.birth[module.start], next.len() - len));
+            copied = module.end;
+        }
+        next.extend(&chars[copied..]);
+        next_birth.extend_from_slice(&self.birth[copied..]);
+
+        self.current_string = next;
+        self.birth = next_birth;
+    }
+
+    /// String length at each recorded iteration, from the history's first to the current
+    /// string, and the iteration the lengths start at
+    pub fn growth(&self) -> (usize, Vec<usize>) {
+        let lengths = self.history.iter().map(|(string, _)| string.chars().count())
+            .chain(std::iter::once(self.current_string.chars().count()))
+            .collect();
+        (self.history_start, lengths)
+    }
+
+    /// The string at `iteration`: from history if the plant has been there, otherwise regrown
+    /// from the axiom (stepping is seeded, so both give the same string)
+    pub fn string_at(&self, iteration: usize) -> String {
+        if iteration == self.iteration {
+            return self.current_string.clone();
+        }
+        if let Some((string, _)) = iteration.checked_sub(self.history_start).and_then(|i| self.history.get(i)) {
+            return string.clone();
+        }
+        let mut plant = Plant::new(self.lsystem.clone(), self.step_size, iteration, self.root_thickness, self.palette.clone())
+            .with_seed(self.seed);
+        plant.environment = self.environment.clone();
+        plant.generator = self.generator.clone();
+        plant.variation = self.variation.clone();
+        while !plant.finished() {
+            plant.step();
+        }
+        plant.current_string
+    }
+
+    /// Branch graph of the current state from the plant's generator. `volume` is the loaded
+    /// attractor mesh, for colonization into a mesh volume.
+    pub fn skeleton(&self, volume: Option<&Mesh>) -> PlantSkeleton {
+        match &self.generator {
+            Generator::LSystem => interpret_plant(&self.current_string, &self.birth, self),
+            Generator::Colonization(settings) => {
+                let steps = self.iteration * settings.steps_per_iteration;
+                let mut skeleton = colonize(settings, self.seed, steps, self.root_thickness, &self.palette, volume);
+                // Every node starts equally thick; the pipe model tapers them from the tips
+                let (exponent, tip_radius) = match self.thickness {
+                    ThicknessModel::Pipe { exponent, tip_radius } => (exponent, tip_radius),
+                    ThicknessModel::Decay => (2.0, self.root_thickness * 0.05),
+                };
+                apply_pipe_model(&mut skeleton, exponent, tip_radius);
+                skeleton
+            }
+        }
     }
 
     /// Check if the plant has finished growing
@@ -84,3 +340,117 @@ impl Plant {
         self.iteration >= self.max_iterations
     }
 }
+
+/// Apply cut symbols: `~` removes itself and the rest of its branch, up to the closing `]`
+fn cut_branches(string: &str, birth: &[u16]) -> (String, Vec<u16>) {
+    let mut out = String::with_capacity(string.len());
+    let mut out_birth = Vec::with_capacity(birth.len());
+    // Bracket depth inside the branch being cut, if any
+    let mut cutting: Option<usize> = None;
+    for (c, &b) in string.chars().zip(birth) {
+        if let Some(depth) = cutting.as_mut() {
+            match c {
+                '[' => *depth += 1,
+                ']' if *depth == 0 => cutting = None,
+                ']' => *depth -= 1,
+                _ => {}
+            }
+            if cutting.is_some() {
+                continue;
+            }
+        } else if c == '~' {
+            cutting = Some(0);
+            continue;
+        }
+        out.push(c);
+        out_birth.push(b);
+    }
+    (out, out_birth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::load_preset;
+
+    #[test]
+    fn stepping_back_matches_a_fresh_grow() {
+        let specimen = load_preset("Flower").unwrap();
+        let mut plant = specimen.to_plant();
+        let steps = plant.max_iterations;
+        assert!(steps >= 3);
+        plant.go_to(steps);
+
+        for k in (0..steps).rev() {
+            plant.go_to(k);
+            let mut fresh = specimen.to_plant();
+            for _ in 0..k {
+                fresh.step();
+            }
+            assert_eq!(plant.iteration, k);
+            assert!(plant.current_string == fresh.current_string, "iteration {k} differs");
+            assert_eq!(plant.birth, fresh.birth);
+        }
+
+        // Forward again from history's end regrows the same string
+        plant.go_to(steps);
+        let mut fresh = specimen.to_plant();
+        fresh.go_to(steps);
+        assert!(plant.current_string == fresh.current_string);
+
+        // Without history the past regrows from the axiom
+        plant.forget_history();
+        for k in [steps - 1, 0] {
+            plant.go_to(steps);
+            plant.forget_history();
+            plant.go_to(k);
+            let mut fresh = Plant::new(plant.lsystem.clone(), plant.step_size, steps, plant.root_thickness, plant.palette.clone())
+                .with_seed(plant.seed);
+            fresh.go_to(k);
+            assert_eq!(plant.iteration, k);
+            assert!(plant.current_string == fresh.current_string && plant.birth == fresh.birth);
+        }
+    }
+
+    #[test]
+    fn unbalanced_brackets_grow_without_panicking() {
+        let rules = vec![Rule::new('A', "F]]A[+F?P(0,0,0)"), Rule::new('F', "F}F")];
+        let palette = load_preset("Flower").unwrap().palette;
+        let mut plant = Plant::new(LSystem::new("]A?P(0,0,0)", rules, 25.0), 10.0, 4, 1.0, palette).with_seed(3);
+        plant.go_to(4);
+        let skeleton = plant.skeleton(None);
+        assert!(!skeleton.nodes.is_empty());
+        assert_eq!(plant.iteration, 4);
+    }
+
+    #[test]
+    fn edited_rules_keep_the_past_straight() {
+        let specimen = load_preset("Flower").unwrap();
+        let mut plant = specimen.to_plant();
+        let edited_at = 2;
+        plant.go_to(edited_at);
+        let held = plant.current_string.clone();
+
+        // What the UI does on a rule edit
+        plant.lsystem.rules[0].successor.push('F');
+        plant.forget_history();
+        plant.go_to(edited_at + 2);
+
+        let mut fresh = Plant::new(plant.lsystem.clone(), plant.step_size, edited_at, plant.root_thickness, plant.palette.clone())
+            .with_seed(plant.seed);
+        for k in 0..edited_at {
+            fresh.go_to(k);
+            assert!(plant.string_at(k) == fresh.current_string, "iteration {k} differs");
+        }
+        assert!(plant.string_at(edited_at) == held);
+
+        let (start, lengths) = plant.growth();
+        assert_eq!(start, edited_at);
+        assert_eq!(lengths.len(), 3);
+        assert_eq!(lengths[0], held.chars().count());
+        assert_eq!(lengths[2], plant.current_string.chars().count());
+        for (i, &length) in lengths.iter().enumerate() {
+            assert_eq!(plant.string_at(start + i).chars().count(), length);
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod presets;
+
+pub use presets::{load_preset, PRESETS};
@@ -0,0 +1,44 @@
+use crate::specimen::Specimen;
+
+/// Bundled species library (name, RON specimen), mostly from
+/// "The Algorithmic Beauty of Plants"
+pub const PRESETS: &[(&str, &str)] = &[
+    ("Flower", include_str!("../../assets/presets/flower.ron")),
+    ("Bush", include_str!("../../assets/presets/bush.ron")),
+    ("Fern", include_str!("../../assets/presets/fern.ron")),
+    ("Koch island", include_str!("../../assets/presets/koch.ron")),
+    ("Tree (tropism)", include_str!("../../assets/presets/tree.ron")),
+    ("Lilac", include_str!("../../assets/presets/lilac.ron")),
+    ("Sunflower", include_str!("../../assets/presets/sunflower.ron")),
+    ("Hilbert curve 3D", include_str!("../../assets/presets/hilbert3d.ron")),
+    ("Topiary (open L-system)", include_str!("../../assets/presets/topiary.ron")),
+    ("Sun seeker (light competition)", include_str!("../../assets/presets/sunseeker.ron")),
+    ("Oak (space colonization)", include_str!("../../assets/presets/colonized_oak.ron")),
+];
+
+/// Parse a bundled preset by name
+pub fn load_preset(name: &str) -> Option<Specimen> {
+    PRESETS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, source)| ron::from_str(source).expect("bundled preset should parse"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_preset_expands_and_interprets() {
+        for (name, _) in PRESETS {
+            let specimen = load_preset(name).unwrap();
+            let mut plant = specimen.to_plant();
+            while !plant.finished() {
+                plant.step();
+            }
+
+            let skeleton = plant.skeleton(None);
+            assert!(!skeleton.nodes.is_empty(), "{name} drew nothing");
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod simulation;
+
+pub use simulation::{run_headless, run_simulation, DispersalKernel, LifeCycle, Simulation, SimulationSettings};
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::ecosystem::{EcosystemMember, EcosystemSettings, Instance};
+use crate::light::{step_competing, LightGrid};
+use crate::plant::{Plant, SelectedPlant};
+use crate::presets::load_preset;
+use crate::skeleton::PlantSkeleton;
+use crate::specimen::Specimen;
+use crate::terrain::Terrain;
+
+/// How far seeds travel from their parent
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DispersalKernel {
+    /// Most seeds land close by; `sigma` is the spread along each axis
+    Gaussian { sigma: f32 },
+    /// A long tail of far-flung seeds; `mean` is the average distance
+    Exponential { mean: f32 },
+}
+
+impl DispersalKernel {
+    /// Distance of one seed from its parent
+    fn sample(&self, rng: &mut StdRng) -> f32 {
+        // In (0, 1], so the logarithm stays finite
+        let u = 1.0 - rng.random::<f32>();
+        match *self {
+            DispersalKernel::Gaussian { sigma } => sigma * (-2.0 * u.ln()).sqrt(),
+            DispersalKernel::Exponential { mean } => -mean * u.ln(),
+        }
+    }
+}
+
+/// How a species reproduces and ages in the simulation
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LifeCycle {
+    /// Every occurrence of this symbol in the string is a flower
+    pub flower: char,
+    /// Seeds each flower sets per generation
+    pub seeds_per_flower: f32,
+    pub dispersal: DispersalKernel,
+    /// Generations before the plant dies of age
+    pub lifespan: u32,
+}
+
+impl Default for LifeCycle {
+    fn default() -> Self {
+        Self {
+            flower: 'O',
+            seeds_per_flower: 0.1,
+            dispersal: DispersalKernel::Gaussian { sigma: 150.0 },
+            lifespan: 10,
+        }
+    }
+}
+
+/// Playback and rules of the simulation
+#[derive(Resource)]
+pub struct SimulationSettings {
+    pub running: bool,
+    pub generations_per_second: f32,
+    /// Self-thinning strength: chance of dying per unit of crowding by plants at least as big
+    pub competition: f32,
+    /// Seeds stop germinating once this many plants are alive
+    pub max_population: usize,
+    /// Start over from the ecosystem's scatter on the next frame
+    pub restart: bool,
+    /// Advance one generation on the next frame, even when paused
+    pub step_once: bool,
+    /// Fraction of the next generation already earned
+    due: f32,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            running: false,
+            generations_per_second: 1.0,
+            competition: 0.5,
+            max_population: 300,
+            restart: false,
+            step_once: false,
+            due: 0.0,
+        }
+    }
+}
+
+/// A plant living in the simulation
+pub struct Individual {
+    /// Unique over the whole run, to find the individual's entity
+    pub id: u64,
+    /// Index into the ecosystem's species list
+    pub species: usize,
+    pub placement: Instance,
+    pub plant: Plant,
+    /// Generations lived
+    pub age: u32,
+}
+
+/// Marks the entity showing the individual with this id
+#[derive(Component)]
+pub struct SimulationMember(pub u64);
+
+/// A meadow evolving over generations: plants grow one iteration per generation, set seed at
+/// their flowers, crowd out smaller neighbours and die of age. Everything is drawn from the
+/// world seed, so a run is the same every time and needs no `World`.
+#[derive(Resource)]
+pub struct Simulation {
+    pub ecosystem: EcosystemSettings,
+    pub terrain: Terrain,
+    pub individuals: Vec<Individual>,
+    pub generation: u32,
+    /// Population of each species after every generation; index 0 is the initial scatter
+    pub census: Vec<Vec<usize>>,
+    /// Each species' specimen, `None` for unknown presets
+    specimens: Vec<Option<Specimen>>,
+    /// Shade cast by the whole meadow, for species competing for light
+    light: LightGrid,
+    rng: StdRng,
+    next_id: u64,
+}
+
+impl Simulation {
+    /// Start from the ecosystem's scatter on its terrain
+    pub fn new(ecosystem: &EcosystemSettings) -> Self {
+        let terrain = Terrain::generate(&ecosystem.terrain, ecosystem.world_seed);
+        let instances = ecosystem.scatter(&terrain);
+        let mut simulation = Self {
+            ecosystem: ecosystem.clone(),
+            terrain,
+            individuals: Vec::new(),
+            generation: 0,
+            census: Vec::new(),
+            specimens: ecosystem.species.iter().map(|s| load_preset(&s.preset)).collect(),
+            light: LightGrid::for_ecosystem(ecosystem),
+            rng: StdRng::seed_from_u64(ecosystem.world_seed ^ 0xD1B5_4A32_D192_ED03),
+            next_id: 0,
+        };
+        for instance in instances {
+            if let Some(species) = ecosystem.species.iter().position(|s| s.preset == instance.species) {
+                simulation.add(species, instance);
+            }
+        }
+        simulation.census.push(simulation.population());
+        simulation
+    }
+
+    fn add(&mut self, species: usize, placement: Instance) {
+        let Some(specimen) = &self.specimens[species] else { return };
+        let mut plant = specimen.to_plant().with_seed(placement.seed);
+        // The simulation steps it, not `plant_step_system`
+        plant.playing = false;
+        self.individuals.push(Individual { id: self.next_id, species, placement, plant, age: 0 });
+        self.next_id += 1;
+    }
+
+    /// Living plants of each species
+    pub fn population(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.ecosystem.species.len()];
+        for individual in &self.individuals {
+            counts[individual.species] += 1;
+        }
+        counts
+    }
+
+    /// Advance one generation
+    pub fn step(&mut self, settings: &SimulationSettings) {
+        self.generation += 1;
+        let plants = self.individuals.iter_mut()
+            .map(|i| (&mut i.plant, GlobalTransform::from(i.placement.transform())));
+        step_competing(plants, &mut self.light);
+        for individual in &mut self.individuals {
+            individual.age += 1;
+        }
+
+        // Every plant flowers before any of them die
+        let mut seeds = Vec::new();
+        for individual in &self.individuals {
+            let life = &self.ecosystem.species[individual.species].life;
+            let flowers = individual.plant.current_string.chars().filter(|&c| c == life.flower).count();
+            let expected = flowers as f32 * life.seeds_per_flower.max(0.0);
+            let count = expected as usize + usize::from(self.rng.random::<f32>() < expected.fract());
+            for _ in 0..count {
+                let direction = Vec2::from_angle(self.rng.random_range(0.0..TAU));
+                let distance = life.dispersal.sample(&mut self.rng);
+                seeds.push((individual.species, individual.placement.position.xz() + direction * distance));
+            }
+        }
+
+        // Self-thinning: a plant is crowded by every neighbour at least its size whose reach
+        // overlaps its own, the more so the bigger and closer the neighbour
+        let reach: Vec<f32> = self.individuals.iter()
+            .map(|i| reach(&i.plant.skeleton(None)) * i.placement.scale)
+            .collect();
+        let dies: Vec<bool> = (0..self.individuals.len()).map(|i| {
+            let individual = &self.individuals[i];
+            if individual.age >= self.ecosystem.species[individual.species].life.lifespan {
+                return true;
+            }
+            let p = individual.placement.position.xz();
+            let crowding: f32 = self.individuals.iter().enumerate()
+                .filter(|&(j, _)| j != i && reach[j] >= reach[i])
+                .map(|(j, other)| {
+                    let distance = p.distance(other.placement.position.xz());
+                    (1.0 - distance / (reach[i] + reach[j])).max(0.0) * reach[j] / reach[i]
+                })
+                .sum();
+            self.rng.random::<f32>() < crowding * settings.competition
+        }).collect();
+        let mut dead = dies.into_iter();
+        self.individuals.retain(|_| !dead.next().unwrap_or(false));
+
+        // Seeds germinate where their species may grow, as the density map allows
+        let mut germinated = Vec::new();
+        {
+            let density = self.ecosystem.density_sampler();
+            for (species, p) in seeds {
+                let kind = &self.ecosystem.species[species];
+                if !self.ecosystem.contains(p) || !kind.mask.allows(self.terrain.altitude(p), self.terrain.slope(p)) {
+                    continue;
+                }
+                if self.rng.random::<f32>() < density(p) {
+                    germinated.push((species, self.ecosystem.instance_at(&kind.preset, p, &self.terrain, &mut self.rng)));
+                }
+            }
+        }
+        // Shuffled so the cap doesn't favour the first parents
+        germinated.shuffle(&mut self.rng);
+        let room = settings.max_population.saturating_sub(self.individuals.len());
+        for (species, placement) in germinated.into_iter().take(room) {
+            self.add(species, placement);
+        }
+
+        self.census.push(self.population());
+    }
+}
+
+/// How far the plant reaches from its base, which sets how far it crowds its neighbours
+fn reach(skeleton: &PlantSkeleton) -> f32 {
+    skeleton.nodes.iter().map(|n| n.position.length()).fold(1.0, f32::max)
+}
+
+fn spawn_individual(commands: &mut Commands, individual: &Individual, species: &str) -> Entity {
+    commands.spawn((
+        individual.plant.clone(),
+        individual.placement.transform(),
+        GlobalTransform::default(),
+        Visibility::default(),
+        EcosystemMember,
+        SimulationMember(individual.id),
+        Name::new(species.to_string()),
+    )).id()
+}
+
+/// Start, advance and show the simulation: after each generation the dead are despawned, the
+/// survivors take their new strings and the seedlings are spawned
+pub fn run_simulation(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut settings: ResMut<SimulationSettings>,
+    ecosystem: Res<EcosystemSettings>,
+    simulation: Option<ResMut<Simulation>>,
+    mut selected: ResMut<SelectedPlant>,
+    mut members: Query<(Entity, Option<&SimulationMember>, &mut Plant), With<EcosystemMember>>,
+) {
+    if std::mem::take(&mut settings.restart) {
+        for (entity, _, _) in &members {
+            commands.entity(entity).despawn();
+        }
+        selected.0 = None;
+        let simulation = Simulation::new(&ecosystem);
+        for individual in &simulation.individuals {
+            let entity = spawn_individual(&mut commands, individual, &simulation.ecosystem.species[individual.species].preset);
+            selected.0.get_or_insert(entity);
+        }
+        settings.due = 0.0;
+        commands.insert_resource(simulation);
+        return;
+    }
+    let Some(mut simulation) = simulation else { return };
+
+    if settings.running {
+        settings.due += time.delta_secs() * settings.generations_per_second;
+    }
+    let generations = settings.due as usize + usize::from(std::mem::take(&mut settings.step_once));
+    settings.due = settings.due.fract();
+    if generations == 0 {
+        return;
+    }
+    for _ in 0..generations {
+        simulation.step(&settings);
+    }
+
+    let mut unseen: HashMap<u64, &Individual> = simulation.individuals.iter().map(|i| (i.id, i)).collect();
+    for (entity, member, mut plant) in &mut members {
+        match member.and_then(|m| unseen.remove(&m.0)) {
+            Some(individual) => {
+                // Only touch plants that grew, so finished ones aren't redrawn
+                if plant.iteration != individual.plant.iteration {
+                    plant.copy_growth(&individual.plant);
+                }
+            }
+            None => {
+                commands.entity(entity).despawn();
+                if selected.0 == Some(entity) {
+                    selected.0 = None;
+                }
+            }
+        }
+    }
+    for individual in simulation.individuals.iter().filter(|i| unseen.contains_key(&i.id)) {
+        let entity = spawn_individual(&mut commands, individual, &simulation.ecosystem.species[individual.species].preset);
+        selected.0.get_or_insert(entity);
+    }
+}
+
+/// Run `generations` of the default ecosystem with `world_seed` and no window, printing the
+/// census as CSV: one row per generation, one column per species
+pub fn run_headless(generations: u32, world_seed: Option<u64>) {
+    let mut ecosystem = EcosystemSettings::default();
+    if let Some(seed) = world_seed {
+        ecosystem.world_seed = seed;
+    }
+    let settings = SimulationSettings::default();
+    let mut simulation = Simulation::new(&ecosystem);
+
+    let names: Vec<&str> = ecosystem.species.iter().map(|s| s.preset.as_str()).collect();
+    println!("generation,{}", names.join(","));
+    let print = |simulation: &Simulation| {
+        let counts: Vec<String> = simulation.census.last().unwrap().iter().map(|n| n.to_string()).collect();
+        println!("{},{}", simulation.generation, counts.join(","));
+    };
+    print(&simulation);
+    for _ in 0..generations {
+        simulation.step(&settings);
+        print(&simulation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulation_is_deterministic() {
+        let ecosystem = EcosystemSettings {
+            region_min: Vec2::splat(-250.0),
+            region_max: Vec2::splat(250.0),
+            ..default()
+        };
+        let settings = SimulationSettings::default();
+        let run = || {
+            let mut simulation = Simulation::new(&ecosystem);
+            for _ in 0..14 {
+                simulation.step(&settings);
+            }
+            let positions: Vec<Vec3> = simulation.individuals.iter().map(|i| i.placement.position).collect();
+            (simulation.census, positions)
+        };
+
+        let (census, positions) = run();
+        // Plants were born and died along the way
+        assert!(census.windows(2).any(|pair| pair[0] != pair[1]));
+        assert!(census.iter().all(|counts| counts.iter().sum::<usize>() <= settings.max_population));
+        assert_eq!((census, positions), run());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod skeleton;
+pub mod rig;
+
+pub use skeleton::{Folio, PlantSkeleton, QueryPoint, SkeletonNode};
+pub use rig::RigSettings;
@@ -0,0 +1,125 @@
+use bevy::{
+    mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::skeleton::PlantSkeleton;
+
+/// Most joints one skinned mesh can use on every backend
+const MAX_JOINTS: usize = 256;
+
+/// Whether to rig plants as a joint hierarchy for skinned animation
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RigSettings {
+    pub enabled: bool,
+    /// Segments bound to one joint before a branch gets another, so long branches can bend
+    pub segments_per_joint: u32,
+}
+
+impl Default for RigSettings {
+    fn default() -> Self {
+        Self { enabled: false, segments_per_joint: 4 }
+    }
+}
+
+/// One bone, in the plant's rest pose
+#[derive(Clone, Copy)]
+pub(crate) struct Joint {
+    pub(crate) parent: Option<usize>,
+    pub(crate) origin: Vec3,
+    pub(crate) rotation: Quat,
+}
+
+impl Joint {
+    /// Rest transform in mesh space
+    pub(crate) fn bind_pose(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.origin)
+    }
+}
+
+/// Joints and the joint each skeleton node is bound to
+pub(crate) struct Rig {
+    pub(crate) joints: Vec<Joint>,
+    segment_joint: Vec<usize>,
+    /// Segment is the first on its joint, so its base blends with the parent joint
+    starts_joint: Vec<bool>,
+}
+
+impl Rig {
+    /// Every lateral branch gets its own chain of joints, following the skeleton's nesting
+    pub(crate) fn new(skeleton: &PlantSkeleton, settings: &RigSettings) -> Self {
+        let per_joint = settings.segments_per_joint.max(1);
+        let mut joints: Vec<Joint> = Vec::new();
+        let mut joint_length: Vec<u32> = Vec::new();
+        let mut segment_joint = Vec::with_capacity(skeleton.nodes.len());
+        let mut starts_joint = Vec::with_capacity(skeleton.nodes.len());
+
+        for (i, node) in skeleton.nodes.iter().enumerate() {
+            let parent_joint = node.parent.map(|p| segment_joint[p]);
+            let new_branch = !skeleton.continues_parent(i);
+            let reuse = joints.len() >= MAX_JOINTS
+                || parent_joint.is_some_and(|j| !new_branch && joint_length[j] < per_joint);
+
+            let joint = if reuse {
+                parent_joint.unwrap_or(0)
+            } else {
+                joints.push(Joint { parent: parent_joint, origin: node.start, rotation: node.frame });
+                joint_length.push(0);
+                joints.len() - 1
+            };
+            joint_length[joint] += 1;
+            segment_joint.push(joint);
+            starts_joint.push(!reuse);
+        }
+
+        Self { joints, segment_joint, starts_joint }
+    }
+
+    /// Joint indices and weights for a vertex at the start or end of `segment`
+    pub(crate) fn segment_skin(&self, segment: usize, at_end: bool) -> ([u16; 4], [f32; 4]) {
+        let joint = self.segment_joint[segment];
+        match self.joints[joint].parent {
+            Some(parent) if self.starts_joint[segment] && !at_end => {
+                ([joint as u16, parent as u16, 0, 0], [0.5, 0.5, 0.0, 0.0])
+            }
+            _ => ([joint as u16, 0, 0, 0], [1.0, 0.0, 0.0, 0.0]),
+        }
+    }
+
+    /// Skin for geometry hanging off `segment`, such as a leaf
+    pub(crate) fn attached_skin(&self, segment: Option<usize>) -> ([u16; 4], [f32; 4]) {
+        let joint = segment.map_or(0, |s| self.segment_joint[s]);
+        ([joint as u16, 0, 0, 0], [1.0, 0.0, 0.0, 0.0])
+    }
+
+    /// Joint transform relative to its parent joint (or the plant for roots)
+    pub(crate) fn local_transform(&self, joint: usize) -> Transform {
+        let j = &self.joints[joint];
+        let parent = j.parent.map_or(Mat4::IDENTITY, |p| self.joints[p].bind_pose());
+        Transform::from_matrix(parent.inverse() * j.bind_pose())
+    }
+
+    pub(crate) fn inverse_bindposes(&self) -> Vec<Mat4> {
+        self.joints.iter().map(|j| j.bind_pose().inverse()).collect()
+    }
+
+    /// Spawn the joint hierarchy under `plant` and return the skin for its meshes
+    pub(crate) fn spawn(
+        &self,
+        commands: &mut Commands,
+        bindposes: &mut Assets<SkinnedMeshInverseBindposes>,
+        plant: Entity,
+    ) -> SkinnedMesh {
+        let mut entities: Vec<Entity> = Vec::with_capacity(self.joints.len());
+        for (i, joint) in self.joints.iter().enumerate() {
+            // Parents always come before their children
+            let parent = joint.parent.map_or(plant, |p| entities[p]);
+            entities.push(commands.spawn((self.local_transform(i), Name::new(format!("Joint{i}")), ChildOf(parent))).id());
+        }
+        SkinnedMesh {
+            inverse_bindposes: bindposes.add(self.inverse_bindposes()),
+            joints: entities,
+        }
+    }
+}
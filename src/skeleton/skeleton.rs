@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+
+use crate::material::SurfaceKind;
+
+/// One drawn segment (`F`) of the branch graph, ending at `position`
+#[derive(Clone)]
+pub struct SkeletonNode {
+    /// Where the segment leading here starts; the parent's position unless the turtle moved with `f`
+    pub start: Vec3,
+    pub position: Vec3,
+    /// Turtle orientation when the segment was drawn; local Y is the heading
+    pub frame: Quat,
+    pub radius: f32,
+    pub color: Color,
+    /// `[` nesting depth of the symbol
+    pub depth: u32,
+    /// Branching order: 0 on the main axis, +1 for every lateral branch off it
+    pub order: u32,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Index of the `F` in the L-system string that drew this node
+    #[allow(dead_code)] // not drawn, but kept so tools can map geometry back to the string
+    pub symbol: usize,
+    /// Arc length from the root to `start`, so bark UVs run on across segments
+    pub distance: f32,
+}
+
+impl SkeletonNode {
+    pub fn length(&self) -> f32 {
+        self.start.distance(self.position)
+    }
+}
+
+/// A leaf or petal polygon (`{ }`)
+#[derive(Clone)]
+pub struct Folio {
+    pub vertices: Vec<Vec3>,
+    pub color: Color,
+    pub kind: SurfaceKind,
+    /// Node the polygon hangs from
+    pub parent: Option<usize>,
+}
+
+/// Turtle state at a query module (`?P`, `?E`) in the string
+#[derive(Clone)]
+pub struct QueryPoint {
+    /// Char index of the module's `?`
+    pub symbol: usize,
+    pub position: Vec3,
+    pub frame: Quat,
+}
+
+/// Branch graph produced by interpreting an L-system string.
+/// Parents always come before their children, so forward passes go root to tip.
+#[derive(Clone, Default)]
+pub struct PlantSkeleton {
+    pub nodes: Vec<SkeletonNode>,
+    pub folios: Vec<Folio>,
+    /// Where the turtle was at each query module, for answering them between steps
+    pub queries: Vec<QueryPoint>,
+}
+
+impl PlantSkeleton {
+    /// Append a node, linking it into its parent's children
+    pub fn push(&mut self, node: SkeletonNode) -> usize {
+        let index = self.nodes.len();
+        if let Some(parent) = node.parent {
+            self.nodes[parent].children.push(index);
+        }
+        self.nodes.push(node);
+        index
+    }
+
+    /// Whether `node` continues its parent's axis rather than starting a lateral branch
+    pub fn continues_parent(&self, node: usize) -> bool {
+        let n = &self.nodes[node];
+        n.parent.is_some_and(|p| self.nodes[p].order == n.order)
+    }
+
+    /// Highest point of any node or polygon, at least 0
+    pub fn height(&self) -> f32 {
+        self.nodes.iter().flat_map(|n| [n.start.y, n.position.y])
+            .chain(self.folios.iter().flat_map(|f| f.vertices.iter().map(|v| v.y)))
+            .fold(0.0, f32::max)
+    }
+
+    /// Rebuild the graph keeping the nodes `keep` accepts. `keep` also gets the nearest kept
+    /// ancestor, which it may extend before dropping a node; dropped nodes hand their
+    /// children and polygons to that ancestor.
+    pub fn filtered(&self, mut keep: impl FnMut(&SkeletonNode, Option<&mut SkeletonNode>) -> bool) -> PlantSkeleton {
+        let mut out = PlantSkeleton::default();
+        let mut remap: Vec<Option<usize>> = vec![None; self.nodes.len()];
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let parent = node.parent.and_then(|p| remap[p]);
+            if !keep(node, parent.map(|p| &mut out.nodes[p])) {
+                remap[i] = parent;
+                continue;
+            }
+            let mut kept = node.clone();
+            kept.parent = parent;
+            kept.children.clear();
+            remap[i] = Some(out.push(kept));
+        }
+
+        out.folios = self.folios.iter()
+            .map(|f| Folio { parent: f.parent.and_then(|p| remap[p]), ..f.clone() })
+            .collect();
+        out
+    }
+}
@@ -0,0 +1,3 @@
+pub mod specimen;
+
+pub use specimen::{Specimen, SpecimenError};
@@ -0,0 +1,161 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::draw::{LodSettings, ThicknessModel};
+use crate::environment::EnvironmentKind;
+use crate::colonization::Generator;
+use crate::lsystem::LSystem;
+use crate::material::PlantMaterials;
+use crate::palette::{ColorMode, Palette};
+use crate::plant::Plant;
+use crate::skeleton::RigSettings;
+use crate::variation::VariationProfile;
+
+/// Everything needed to regrow a plant, as stored on disk
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Specimen {
+    pub lsystem: LSystem,
+    pub step_size: f32,
+    pub root_thickness: f32,
+    #[serde(default)]
+    pub thickness: ThicknessModel,
+    pub max_iterations: usize,
+    pub palette: Palette,
+    #[serde(default)]
+    pub color_mode: ColorMode,
+    #[serde(default)]
+    pub materials: PlantMaterials,
+    #[serde(default)]
+    pub lod: LodSettings,
+    #[serde(default)]
+    pub rig: RigSettings,
+    pub seed: u64,
+    #[serde(default)]
+    pub variation: VariationProfile,
+    #[serde(default)]
+    pub environment: EnvironmentKind,
+    #[serde(default)]
+    pub generator: Generator,
+}
+
+#[derive(Debug)]
+pub enum SpecimenError {
+    Io(io::Error),
+    Ron(String),
+    Json(serde_json::Error),
+    /// Well-formed but unusable settings
+    Invalid(String),
+}
+
+impl fmt::Display for SpecimenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecimenError::Io(e) => write!(f, "i/o error: {e}"),
+            SpecimenError::Ron(e) => write!(f, "RON error: {e}"),
+            SpecimenError::Json(e) => write!(f, "JSON error: {e}"),
+            SpecimenError::Invalid(e) => write!(f, "invalid settings: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for SpecimenError {
+    fn from(e: io::Error) -> Self {
+        SpecimenError::Io(e)
+    }
+}
+
+impl Specimen {
+    /// Capture the tunable parts of a plant (not its growth state)
+    pub fn from_plant(plant: &Plant) -> Self {
+        Self {
+            lsystem: plant.lsystem.clone(),
+            step_size: plant.step_size,
+            root_thickness: plant.root_thickness,
+            thickness: plant.thickness,
+            max_iterations: plant.max_iterations,
+            palette: plant.palette.clone(),
+            color_mode: plant.color_mode,
+            materials: plant.materials.clone(),
+            lod: plant.lod.clone(),
+            rig: plant.rig.clone(),
+            seed: plant.seed,
+            variation: plant.variation.clone(),
+            environment: plant.environment.clone(),
+            generator: plant.generator.clone(),
+        }
+    }
+
+    /// Build a fresh plant at iteration 0
+    pub fn to_plant(&self) -> Plant {
+        let mut plant = Plant::new(
+            self.lsystem.clone(),
+            self.step_size,
+            self.max_iterations,
+            self.root_thickness,
+            self.palette.clone(),
+        )
+        .with_seed(self.seed);
+        plant.thickness = self.thickness;
+        plant.color_mode = self.color_mode;
+        plant.materials = self.materials.clone();
+        plant.lod = self.lod.clone();
+        plant.rig = self.rig.clone();
+        plant.variation = self.variation.clone();
+        plant.environment = self.environment.clone();
+        plant.generator = self.generator.clone();
+        plant
+    }
+
+    /// Write as JSON if the extension is `.json`, RON otherwise
+    pub fn save(&self, path: &Path) -> Result<(), SpecimenError> {
+        let text = if is_json(path) {
+            serde_json::to_string_pretty(self).map_err(SpecimenError::Json)?
+        } else {
+            ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|e| SpecimenError::Ron(e.to_string()))?
+        };
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Read a specimen, picking the format from the extension like `save`
+    pub fn load(path: &Path) -> Result<Self, SpecimenError> {
+        let text = fs::read_to_string(path)?;
+        if is_json(path) {
+            serde_json::from_str(&text).map_err(SpecimenError::Json)
+        } else {
+            ron::from_str(&text).map_err(|e| SpecimenError::Ron(e.to_string()))
+        }
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::{load_preset, PRESETS};
+
+    #[test]
+    fn specimens_survive_save_and_load() {
+        let text = |specimen: &Specimen| ron::to_string(specimen).unwrap();
+        for (name, _) in PRESETS {
+            let mut specimen = load_preset(name).unwrap();
+            specimen.seed = 42;
+            for extension in ["ron", "json"] {
+                let path = std::env::temp_dir().join(format!("bevy_lsystem_specimen_{}.{extension}", std::process::id()));
+                specimen.save(&path).unwrap();
+                let loaded = Specimen::load(&path);
+                std::fs::remove_file(&path).unwrap();
+                assert_eq!(text(&loaded.unwrap()), text(&specimen), "{name} as {extension}");
+            }
+
+            // The plant built from it captures the same settings again
+            assert_eq!(text(&Specimen::from_plant(&specimen.to_plant())), text(&specimen), "{name} from its plant");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::plant::Plant;
+use crate::skeleton::PlantSkeleton;
+
+/// Introspection numbers for one plant, refreshed whenever it steps or is redrawn
+#[derive(Component, Default)]
+pub struct PlantStats {
+    /// String length after each iteration from `growth_start` on
+    pub growth: Vec<usize>,
+    /// Iteration of `growth[0]`; 0 (the axiom) unless the rules changed mid-growth
+    pub growth_start: usize,
+    /// Count of each symbol in the current string, most frequent first
+    pub symbols: Vec<(char, usize)>,
+    pub segments: usize,
+    pub leaves: usize,
+    /// Full-detail mesh totals
+    pub vertices: usize,
+    pub triangles: usize,
+    /// Deepest `[` nesting and highest branching order reached
+    pub max_depth: u32,
+    pub max_order: u32,
+    pub bounds: Option<(Vec3, Vec3)>,
+    pub step_time: Duration,
+    pub interpret_time: Duration,
+    pub mesh_time: Duration,
+}
+
+impl PlantStats {
+    /// Record the plant's growth so far and the symbols of its current string
+    pub fn record_string(&mut self, plant: &Plant) {
+        (self.growth_start, self.growth) = plant.growth();
+
+        let mut counts: HashMap<char, usize> = HashMap::new();
+        for c in plant.current_string.chars() {
+            *counts.entry(c).or_default() += 1;
+        }
+        let mut counts: Vec<(char, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        self.symbols = counts;
+    }
+
+    pub fn record_skeleton(&mut self, skeleton: &PlantSkeleton) {
+        self.segments = skeleton.nodes.len();
+        self.leaves = skeleton.folios.len();
+        self.max_depth = skeleton.nodes.iter().map(|n| n.depth).max().unwrap_or(0);
+        self.max_order = skeleton.nodes.iter().map(|n| n.order).max().unwrap_or(0);
+
+        let points = skeleton.nodes.iter().flat_map(|n| [n.start, n.position])
+            .chain(skeleton.folios.iter().flat_map(|f| f.vertices.iter().copied()));
+        self.bounds = points.fold(None, |bounds, p| match bounds {
+            Some((min, max)) => Some((Vec3::min(min, p), Vec3::max(max, p))),
+            None => Some((p, p)),
+        });
+    }
+
+    pub fn height(&self) -> f32 {
+        self.bounds.map_or(0.0, |(min, max)| max.y - min.y)
+    }
+}
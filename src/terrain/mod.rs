@@ -0,0 +1,3 @@
+pub mod terrain;
+
+pub use terrain::{HeightSource, Terrain, TerrainMesh, TerrainSettings};
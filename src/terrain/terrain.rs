@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::Indices,
+    prelude::*,
+    render::render_resource::PrimitiveTopology,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::field::{fbm, GrayImage};
+
+/// Where terrain heights come from
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum HeightSource {
+    Flat,
+    /// Fractal noise seeded by the world seed; `frequency` is hills per 1000 units
+    Noise { frequency: f32, octaves: u32 },
+    /// Grayscale heightmap (16-bit PNG keeps full precision) stretched over the terrain
+    Image { path: String },
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerrainSettings {
+    pub source: HeightSource,
+    /// Side of the square terrain, centred on the origin
+    pub size: f32,
+    /// Height of the lowest point
+    pub base: f32,
+    /// Height difference between the lowest and highest point
+    pub relief: f32,
+    /// Quads per side of the terrain mesh and height grid
+    pub resolution: u32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            source: HeightSource::Noise { frequency: 2.0, octaves: 4 },
+            size: 1000.0,
+            base: -200.0,
+            relief: 120.0,
+            resolution: 128,
+        }
+    }
+}
+
+/// Marks the spawned terrain mesh
+#[derive(Component)]
+pub struct TerrainMesh;
+
+/// Sampled heightfield, for placing plants and meshing the ground
+#[derive(Resource, Clone)]
+pub struct Terrain {
+    min: Vec2,
+    size: f32,
+    base: f32,
+    relief: f32,
+    resolution: u32,
+    /// (resolution + 1)² heights in 0..1, row by row along +Z
+    heights: Vec<f32>,
+}
+
+impl Terrain {
+    pub fn generate(settings: &TerrainSettings, seed: u64) -> Self {
+        let resolution = settings.resolution.max(1);
+        let size = settings.size.max(1.0);
+        let image = match &settings.source {
+            HeightSource::Image { path } => GrayImage::load(Path::new(path))
+                .inspect_err(|e| warn!("could not load heightmap {path}: {e}"))
+                .ok(),
+            _ => None,
+        };
+
+        let mut heights = Vec::with_capacity(((resolution + 1) * (resolution + 1)) as usize);
+        for z in 0..=resolution {
+            for x in 0..=resolution {
+                let uv = UVec2::new(x, z).as_vec2() / resolution as f32;
+                heights.push(match &settings.source {
+                    HeightSource::Flat => 0.0,
+                    HeightSource::Noise { frequency, octaves } => fbm(uv * size * frequency / 1000.0, *octaves, seed),
+                    HeightSource::Image { .. } => image.as_ref().map_or(0.0, |image| image.sample(uv)),
+                });
+            }
+        }
+
+        // Stretch noise to use the full relief
+        if matches!(settings.source, HeightSource::Noise { .. }) {
+            let (low, high) = heights.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &h| (lo.min(h), hi.max(h)));
+            let range = (high - low).max(f32::EPSILON);
+            heights.iter_mut().for_each(|h| *h = (*h - low) / range);
+        }
+
+        Self { min: Vec2::splat(-size * 0.5), size, base: settings.base, relief: settings.relief, resolution, heights }
+    }
+
+    /// Height in 0..1 of the relief at world `xz`, bilinear, clamped at the edges
+    pub fn altitude(&self, xz: Vec2) -> f32 {
+        let res = self.resolution;
+        let p = ((xz - self.min) / self.size * res as f32).clamp(Vec2::ZERO, Vec2::splat(res as f32));
+        let (x0, z0) = (p.x.floor() as u32, p.y.floor() as u32);
+        let (x1, z1) = ((x0 + 1).min(res), (z0 + 1).min(res));
+        let at = |x: u32, z: u32| self.heights[(z * (res + 1) + x) as usize];
+        let f = p - p.floor();
+        let near = at(x0, z0) + (at(x1, z0) - at(x0, z0)) * f.x;
+        let far = at(x0, z1) + (at(x1, z1) - at(x0, z1)) * f.x;
+        near + (far - near) * f.y
+    }
+
+    /// World height of the surface at `xz`
+    pub fn height(&self, xz: Vec2) -> f32 {
+        self.base + self.altitude(xz) * self.relief
+    }
+
+    /// Unit surface normal at `xz`
+    pub fn normal(&self, xz: Vec2) -> Vec3 {
+        let h = self.size / self.resolution as f32;
+        let dx = self.height(xz + Vec2::X * h) - self.height(xz - Vec2::X * h);
+        let dz = self.height(xz + Vec2::Y * h) - self.height(xz - Vec2::Y * h);
+        Vec3::new(-dx, 2.0 * h, -dz).normalize()
+    }
+
+    /// Steepness at `xz` in degrees, 0 on flat ground
+    pub fn slope(&self, xz: Vec2) -> f32 {
+        self.normal(xz).y.clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        let res = self.resolution;
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        for z in 0..=res {
+            for x in 0..=res {
+                let uv = UVec2::new(x, z).as_vec2() / res as f32;
+                let xz = self.min + uv * self.size;
+                positions.push([xz.x, self.height(xz), xz.y]);
+                normals.push(self.normal(xz).to_array());
+                uvs.push(uv.to_array());
+            }
+        }
+
+        let mut indices = Vec::new();
+        for z in 0..res {
+            for x in 0..res {
+                let i = z * (res + 1) + x;
+                let j = i + res + 1;
+                indices.extend_from_slice(&[i, j, i + 1, i + 1, j, j + 1]);
+            }
+        }
+
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100 × 100 terrain whose heights come from `f(x, z)` at the grid points, 10 apart
+    fn terrain(f: impl Fn(u32, u32) -> f32) -> Terrain {
+        let resolution = 10;
+        let heights = (0..=resolution).flat_map(|z| (0..=resolution).map(move |x| (x, z))).map(|(x, z)| f(x, z)).collect();
+        Terrain { min: Vec2::splat(-50.0), size: 100.0, base: -20.0, relief: 100.0, resolution, heights }
+    }
+
+    #[test]
+    fn heights_are_bilinear_and_clamped() {
+        // A saddle: high on two opposite corners of every cell
+        let saddle = terrain(|x, z| ((x + z) % 2) as f32);
+        assert_eq!(saddle.altitude(Vec2::new(-50.0, -50.0)), 0.0);
+        assert_eq!(saddle.altitude(Vec2::new(-40.0, -50.0)), 1.0);
+        assert!((saddle.altitude(Vec2::new(-45.0, -45.0)) - 0.5).abs() < 1e-5);
+        assert!((saddle.altitude(Vec2::new(-47.5, -47.5)) - 0.375).abs() < 1e-5);
+        assert!((saddle.altitude(Vec2::new(-47.5, -50.0)) - 0.25).abs() < 1e-5);
+        // Outside the terrain the edge carries on
+        assert_eq!(saddle.altitude(Vec2::new(-500.0, -40.0)), saddle.altitude(Vec2::new(-50.0, -40.0)));
+        assert_eq!(saddle.altitude(Vec2::new(500.0, 500.0)), saddle.altitude(Vec2::new(50.0, 50.0)));
+        assert!((saddle.height(Vec2::new(-45.0, -45.0)) - 30.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn normals_and_slopes_follow_the_ground() {
+        let flat = Terrain::generate(&TerrainSettings { source: HeightSource::Flat, ..default() }, 1);
+        assert_eq!(flat.normal(Vec2::new(12.0, -30.0)), Vec3::Y);
+        assert_eq!(flat.slope(Vec2::ZERO), 0.0);
+
+        // Rises one unit per unit along +X: 45°, facing back down towards -X
+        let ramp = terrain(|x, _| x as f32 / 10.0);
+        let normal = ramp.normal(Vec2::new(5.0, 5.0));
+        assert!(normal.abs_diff_eq(Vec3::new(-1.0, 1.0, 0.0).normalize(), 1e-5));
+        assert!((ramp.slope(Vec2::new(-20.0, 30.0)) - 45.0).abs() < 1e-3);
+
+        // A quarter as steep along +Z
+        let gentle = terrain(|_, z| z as f32 / 40.0);
+        assert!((gentle.slope(Vec2::ZERO) - 0.25f32.atan().to_degrees()).abs() < 1e-3);
+        assert!(gentle.normal(Vec2::ZERO).z < 0.0);
+    }
+}
@@ -1,4 +1,6 @@
 
 pub mod ui;
+pub mod string_viewer;
 
-pub use ui::{plant_ui, palette_ui};
+pub use ui::{plant_ui, palette_ui, specimen_ui, variation_ui, material_ui, wind_ui, stats_ui, growth_ui, ecosystem_ui, simulation_ui, breeding_ui, SpecimenFile};
+pub use string_viewer::{string_viewer_ui, StringViewer};
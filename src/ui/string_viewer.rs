@@ -0,0 +1,445 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::lsystem::Rule;
+use crate::plant::{Plant, SelectedPlant};
+
+/// Visible characters per row
+const ROW_CHARS: usize = 96;
+const FOLDED_LABEL: &str = "[…]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum SymbolClass {
+    Drawing,
+    Turning,
+    Branching,
+    Leaf,
+    Unknown,
+}
+
+impl SymbolClass {
+    fn of(c: char) -> Self {
+        match c {
+            'F' | 'f' | '!' | '\'' => SymbolClass::Drawing,
+            '+' | '-' | '&' | '^' | '\\' | '/' | '|' => SymbolClass::Turning,
+            '[' | ']' => SymbolClass::Branching,
+            '{' | '}' | '%' => SymbolClass::Leaf,
+            _ => SymbolClass::Unknown,
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            SymbolClass::Drawing => egui::Color32::from_rgb(222, 184, 135),
+            SymbolClass::Turning => egui::Color32::from_rgb(120, 170, 255),
+            SymbolClass::Branching => egui::Color32::from_rgb(255, 200, 80),
+            SymbolClass::Leaf => egui::Color32::from_rgb(120, 220, 120),
+            SymbolClass::Unknown => egui::Color32::GRAY,
+        }
+    }
+}
+
+/// Part of a display row
+enum Piece {
+    /// Byte range of the shown string
+    Text(Range<usize>),
+    /// A folded `[...]`, by the byte offsets of its brackets
+    Folded { open: usize, close: usize },
+}
+
+impl Piece {
+    fn width(&self, text: &str) -> usize {
+        match self {
+            Piece::Text(range) => text[range.clone()].chars().count(),
+            Piece::Folded { .. } => FOLDED_LABEL.chars().count(),
+        }
+    }
+}
+
+/// What a past string was regrown from
+#[derive(PartialEq)]
+struct Source {
+    entity: Entity,
+    iteration: usize,
+    seed: u64,
+    axiom: String,
+    rules: Vec<Rule>,
+}
+
+impl Source {
+    fn new(entity: Entity, iteration: usize, plant: &Plant) -> Self {
+        Self {
+            entity,
+            iteration,
+            seed: plant.seed,
+            axiom: plant.lsystem.axiom.clone(),
+            rules: plant.lsystem.rules.clone(),
+        }
+    }
+}
+
+/// State of the string viewer window
+#[derive(Resource, Default)]
+pub struct StringViewer {
+    /// Iteration to show; `None` follows the plant
+    pub iteration: Option<usize>,
+    pub search: String,
+    /// Copy of the string on screen, so rows only rebuild when it changes
+    shown: String,
+    past: Option<Source>,
+    /// Byte offsets of folded `[`
+    folded: HashSet<usize>,
+    rows: Vec<Vec<Piece>>,
+    dirty: bool,
+    /// Byte offset of the current search match
+    found: Option<usize>,
+    scroll_to_found: bool,
+}
+
+impl StringViewer {
+    fn show(&mut self, text: &str) {
+        if self.shown != text {
+            self.shown = text.to_string();
+            self.folded.clear();
+            self.found = None;
+            self.dirty = true;
+        }
+    }
+
+    /// Byte offset of the matching `]` for every `[` that has one
+    fn brackets(&self) -> HashMap<usize, usize> {
+        let mut open = Vec::new();
+        let mut pairs = HashMap::new();
+        for (i, c) in self.shown.char_indices() {
+            match c {
+                '[' => open.push(i),
+                ']' => {
+                    if let Some(start) = open.pop() {
+                        pairs.insert(start, i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        pairs
+    }
+
+    fn layout(&mut self) {
+        let pairs = self.brackets();
+        self.folded.retain(|open| pairs.contains_key(open));
+
+        let text = &self.shown;
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut width = 0;
+        let mut run = 0;
+        let mut i = 0;
+
+        while i < text.len() {
+            let c = text[i..].chars().next().unwrap();
+            if c == '[' && self.folded.contains(&i) {
+                if run < i {
+                    row.push(Piece::Text(run..i));
+                }
+                let close = pairs[&i];
+                row.push(Piece::Folded { open: i, close });
+                width += FOLDED_LABEL.chars().count();
+                i = close + 1;
+                run = i;
+            } else {
+                width += 1;
+                i += c.len_utf8();
+            }
+
+            if width >= ROW_CHARS {
+                if run < i {
+                    row.push(Piece::Text(run..i));
+                }
+                rows.push(std::mem::take(&mut row));
+                width = 0;
+                run = i;
+            }
+        }
+        if run < text.len() {
+            row.push(Piece::Text(run..text.len()));
+        }
+        if !row.is_empty() {
+            rows.push(row);
+        }
+
+        self.rows = rows;
+        self.dirty = false;
+    }
+
+    fn fold_all(&mut self) {
+        self.folded = self.shown.char_indices().filter(|&(_, c)| c == '[').map(|(i, _)| i).collect();
+        self.dirty = true;
+    }
+
+    fn find_next(&mut self) {
+        if self.search.is_empty() {
+            return;
+        }
+        let from = self.found.map_or(0, |f| f + 1).min(self.shown.len());
+        let found = self.shown.get(from..).and_then(|rest| rest.find(&self.search)).map(|i| i + from)
+            .or_else(|| self.shown.find(&self.search));
+
+        if let Some(pos) = found {
+            // Unfold every branch that hides the match
+            let pairs = self.brackets();
+            self.folded.retain(|open| !(*open < pos && pos <= pairs[open]));
+            self.dirty = true;
+            self.scroll_to_found = true;
+        }
+        self.found = found;
+    }
+
+    fn row_of(&self, pos: usize) -> Option<usize> {
+        self.rows.iter().position(|row| row.iter().any(|piece| match piece {
+            Piece::Text(range) => range.contains(&pos),
+            Piece::Folded { open, close } => (*open..=*close).contains(&pos),
+        }))
+    }
+
+    fn row_job(&self, row: &[Piece], font: &egui::FontId) -> egui::text::LayoutJob {
+        let mut job = egui::text::LayoutJob::default();
+        let matched = self.found.map(|f| f..f + self.search.len());
+
+        for piece in row {
+            match piece {
+                Piece::Text(range) => {
+                    for (offset, c) in self.shown[range.clone()].char_indices() {
+                        let highlighted = matched.as_ref().is_some_and(|m| m.contains(&(range.start + offset)));
+                        let format = egui::TextFormat {
+                            font_id: font.clone(),
+                            color: SymbolClass::of(c).color(),
+                            background: if highlighted { egui::Color32::from_rgb(90, 70, 0) } else { egui::Color32::TRANSPARENT },
+                            ..default()
+                        };
+                        job.append(c.encode_utf8(&mut [0; 4]), 0.0, format);
+                    }
+                }
+                Piece::Folded { .. } => {
+                    let format = egui::TextFormat {
+                        font_id: font.clone(),
+                        color: SymbolClass::Branching.color(),
+                        background: egui::Color32::from_gray(60),
+                        ..default()
+                    };
+                    job.append(FOLDED_LABEL, 0.0, format);
+                }
+            }
+        }
+        job
+    }
+
+    /// Clicking a `[` folds its branch; clicking a folded branch opens it
+    fn click(&mut self, row: usize, column: usize) {
+        let mut start = 0;
+        for piece in &self.rows[row] {
+            let width = piece.width(&self.shown);
+            if column < start + width {
+                match piece {
+                    Piece::Folded { open, .. } => {
+                        self.folded.remove(open);
+                    }
+                    Piece::Text(range) => {
+                        let (offset, c) = self.shown[range.clone()].char_indices().nth(column - start).unwrap();
+                        if c == '[' {
+                            self.folded.insert(range.start + offset);
+                        }
+                    }
+                }
+                self.dirty = true;
+                return;
+            }
+            start += width;
+        }
+    }
+}
+
+pub fn string_viewer_ui(
+    mut contexts: EguiContexts,
+    mut viewer: ResMut<StringViewer>,
+    selected: Res<SelectedPlant>,
+    query: Query<&Plant>,
+) {
+    let viewer = &mut *viewer;
+    egui::Window::new("String").show(contexts.ctx_mut().unwrap(), |ui| {
+        let Some((entity, plant)) = selected.0.and_then(|e| query.get(e).ok().map(|p| (e, p))) else {
+            ui.label("No plant selected");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let mut past = viewer.iteration.is_some();
+            ui.checkbox(&mut past, "Past iteration");
+            match (past, viewer.iteration) {
+                (true, None) => viewer.iteration = Some(plant.iteration),
+                (false, Some(_)) => viewer.iteration = None,
+                _ => {}
+            }
+            if let Some(iteration) = &mut viewer.iteration {
+                *iteration = (*iteration).min(plant.iteration);
+                ui.add(egui::Slider::new(iteration, 0..=plant.iteration));
+            }
+        });
+
+        match viewer.iteration {
+            Some(iteration) => {
+                // Stepping is seeded, so regrowing gives exactly the string the plant had
+                let source = Source::new(entity, iteration, plant);
+                if viewer.past.as_ref() != Some(&source) {
+                    let text = plant.string_at(iteration);
+                    viewer.show(&text);
+                    viewer.past = Some(source);
+                }
+            }
+            None => {
+                viewer.past = None;
+                viewer.show(&plant.current_string);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            let response = ui.text_edit_singleline(&mut viewer.search);
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Find next").clicked() || enter {
+                viewer.find_next();
+            }
+            if response.changed() {
+                viewer.found = None;
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Fold all").clicked() {
+                viewer.fold_all();
+            }
+            if ui.button("Unfold all").clicked() {
+                viewer.folded.clear();
+                viewer.dirty = true;
+            }
+            ui.label(format!("{} chars", viewer.shown.chars().count()));
+        });
+
+        if viewer.dirty {
+            viewer.layout();
+        }
+
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let row_height = ui.fonts_mut(|f| f.row_height(&font));
+        let mut area = egui::ScrollArea::both().max_height(400.0).auto_shrink([false, true]);
+        if std::mem::take(&mut viewer.scroll_to_found)
+            && let Some(row) = viewer.found.and_then(|pos| viewer.row_of(pos))
+        {
+            area = area.vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+
+        // Only the visible rows are laid out, however long the string is
+        let mut clicked = None;
+        area.show_rows(ui, row_height, viewer.rows.len(), |ui, rows| {
+            for row in rows {
+                let job = viewer.row_job(&viewer.rows[row], &font);
+                let galley = ui.fonts_mut(|f| f.layout_job(job));
+                let (rect, response) = ui.allocate_exact_size(galley.size(), egui::Sense::click());
+                if response.clicked()
+                    && let Some(pointer) = response.interact_pointer_pos()
+                {
+                    clicked = Some((row, galley.cursor_from_pos(pointer - rect.min).index));
+                }
+                ui.painter().galley(rect.min, galley, egui::Color32::WHITE);
+            }
+        });
+        if let Some((row, column)) = clicked {
+            viewer.click(row, column);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer(text: &str) -> StringViewer {
+        let mut viewer = StringViewer::default();
+        viewer.show(text);
+        viewer.layout();
+        viewer
+    }
+
+    /// The rows as they read on screen
+    fn rows(viewer: &mut StringViewer) -> Vec<String> {
+        if viewer.dirty {
+            viewer.layout();
+        }
+        viewer.rows.iter().map(|row| row.iter().map(|piece| match piece {
+            Piece::Text(range) => &viewer.shown[range.clone()],
+            Piece::Folded { .. } => FOLDED_LABEL,
+        }).collect()).collect()
+    }
+
+    #[test]
+    fn clicks_fold_and_unfold_branches() {
+        let mut viewer = viewer("F[A[B]C]D");
+        viewer.fold_all();
+        assert_eq!(rows(&mut viewer), ["F[…]D"]);
+
+        // Opening the outer branch leaves the inner one folded
+        viewer.click(0, 1);
+        assert_eq!(rows(&mut viewer), ["F[A[…]C]D"]);
+        viewer.click(0, 3);
+        assert_eq!(rows(&mut viewer), ["F[A[B]C]D"]);
+        // Clicking a `[` folds it, clicking anything else does nothing
+        viewer.click(0, 3);
+        assert_eq!(rows(&mut viewer), ["F[A[…]C]D"]);
+        viewer.click(0, 0);
+        assert_eq!(rows(&mut viewer), ["F[A[…]C]D"]);
+
+        // A new string starts unfolded
+        viewer.show("F[A]");
+        assert_eq!(rows(&mut viewer), ["F[A]"]);
+    }
+
+    #[test]
+    fn search_unfolds_matches_and_wraps_around() {
+        let mut viewer = viewer("AB[AB[AB]]AB");
+        viewer.search = "AB".to_string();
+        viewer.fold_all();
+
+        let mut found = Vec::new();
+        let mut shown = Vec::new();
+        for _ in 0..5 {
+            viewer.find_next();
+            found.push(viewer.found.unwrap());
+            shown.push(rows(&mut viewer).concat());
+        }
+        assert_eq!(found, [0, 3, 6, 10, 0]);
+        // Only the branches around the match open
+        assert_eq!(shown[0], "AB[…]AB");
+        assert_eq!(shown[1], "AB[AB[…]]AB");
+        assert_eq!(shown[2], "AB[AB[AB]]AB");
+        assert_eq!(viewer.row_of(6), Some(0));
+
+        viewer.search = "Q".to_string();
+        viewer.find_next();
+        assert_eq!(viewer.found, None);
+    }
+
+    #[test]
+    fn long_strings_split_into_rows() {
+        let mut viewer = viewer(&"F".repeat(200));
+        let widths: Vec<usize> = rows(&mut viewer).iter().map(|r| r.chars().count()).collect();
+        assert_eq!(widths, [ROW_CHARS, ROW_CHARS, 200 - 2 * ROW_CHARS]);
+        assert_eq!(viewer.row_of(ROW_CHARS), Some(1));
+
+        // A folded branch counts as its label, and a row ends after it once full
+        let text = format!("{}[{}]FFFFF", "F".repeat(ROW_CHARS - 1), "X".repeat(300));
+        let mut viewer = self::viewer(&text);
+        viewer.fold_all();
+        assert_eq!(rows(&mut viewer), [format!("{}[…]", "F".repeat(ROW_CHARS - 1)), "FFFFF".to_string()]);
+        assert_eq!(viewer.row_of(ROW_CHARS + 10), Some(0));
+    }
+}
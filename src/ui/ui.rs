@@ -1,126 +1,1079 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
 use bevy::prelude::*;
 use bevy_egui::EguiContexts;
-use crate::plant::Plant;
-use crate::lsystem::Rule;
+use crate::plant::{GrowthClock, GrowthTimer, Plant, SelectedPlant};
+use crate::draw::ThicknessModel;
+use crate::palette::{ColorMode, ColorStop, Palette};
+use crate::material::SurfaceMaterial;
+use crate::lsystem::{AxisAngle, Condition, Rule};
+use crate::environment::EnvironmentKind;
+use crate::colonization::{AttractorVolume, ColonizationSettings, Generator, VolumeMeshes};
+use crate::ecosystem::{DensityMap, EcosystemSettings, SpeciesMask, SpeciesWeight};
+use crate::terrain::HeightSource;
+use crate::evolution::{Breeding, Offspring};
+use crate::simulation::{DispersalKernel, LifeCycle, Simulation, SimulationSettings};
+use crate::specimen::Specimen;
+use crate::export::export_glb;
+use crate::presets::{load_preset, PRESETS};
+use crate::variation::{Distribution, Jitter, SymbolVariation};
+use crate::wind::Wind;
+use crate::stats::PlantStats;
 use bevy_egui::egui;
 
-pub fn plant_ui(mut contexts: EguiContexts, mut query: Query<&mut Plant>) {
+/// State of the specimen save/open panel
+#[derive(Resource)]
+pub struct SpecimenFile {
+    pub path: String,
+    pub replace_selected: bool,
+    pub status: String,
+}
+
+impl Default for SpecimenFile {
+    fn default() -> Self {
+        Self {
+            path: "specimen.ron".to_string(),
+            replace_selected: true,
+            status: String::new(),
+        }
+    }
+}
+
+/// Hash of what the plant panels edit that needs a redraw: settings and growth state
+fn plant_fingerprint(plant: &Plant) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    ron::to_string(&Specimen::from_plant(plant)).unwrap_or_default().hash(&mut hasher);
+    (plant.iteration, plant.current_string.len()).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Show a panel on the plant without tripping change detection, which every `&mut` field
+/// handed to a widget would do each frame; the plant is only flagged (and redrawn) when the
+/// panel actually changed it. Edits to what growth depends on make the recorded steps stale,
+/// so they're dropped.
+fn edit_plant(plant: &mut Mut<Plant>, panel: impl FnOnce(&mut Plant)) {
+    let before = plant_fingerprint(plant);
+    let growth = plant.growth_fingerprint();
+    panel(plant.bypass_change_detection());
+    if plant.growth_fingerprint() != growth {
+        plant.bypass_change_detection().forget_history();
+    }
+    if plant_fingerprint(plant) != before {
+        plant.set_changed();
+    }
+}
+
+pub fn plant_ui(mut contexts: EguiContexts, mut selected: ResMut<SelectedPlant>, mut query: Query<(Entity, &mut Plant)>) {
     egui::Window::new("Plant Settings").show(contexts.ctx_mut().unwrap(), |ui| {
-        for mut plant in query.iter_mut() {
-            ui.label("Adjust step size:");
-            ui.add(
-                egui::Slider::new(&mut plant.step_size, 1.0..=50.0)
-                    .text("Step Size")
-            );
-
-            ui.label("Root Thickness:");
-            ui.add(
-                egui::Slider::new(&mut plant.root_thickness, 1.0..=10.0)
-                    .text("Thickness")
-            );
-
-            ui.label("Max Iterations:");
-            ui.add(
-                egui::Slider::new(&mut plant.max_iterations, 1..=10)
-                    .text("Max Iterations")
-            );
-
-            ui.label("Axiom:");
-            ui.text_edit_singleline(&mut plant.lsystem.axiom);
+        let entities: Vec<Entity> = query.iter().map(|(e, _)| e).collect();
 
-            ui.separator();
-            ui.label("Rules (format: F -> F+F-F):");
+        // Fall back to the first plant if nothing (or a despawned plant) is selected
+        if selected.0.is_none_or(|e| !entities.contains(&e)) {
+            selected.0 = entities.first().copied();
+        }
 
-            let mut remove_index: Option<usize> = None;
+        egui::ComboBox::from_label("Plant")
+            .selected_text(selected.0.map_or("None".to_string(), |e| format!("{e}")))
+            .show_ui(ui, |ui| {
+                for e in &entities {
+                    ui.selectable_value(&mut selected.0, Some(*e), format!("{e}"));
+                }
+            });
+        ui.separator();
+
+        if let Some(mut plant) = selected.0.and_then(|e| query.get_mut(e).ok()).map(|(_, p)| p) {
+            edit_plant(&mut plant, |plant| {
+                egui::ComboBox::from_label("Preset")
+                    .selected_text("Choose species…")
+                    .show_ui(ui, |ui| {
+                        for (name, _) in PRESETS {
+                            if ui.selectable_label(false, *name).clicked()
+                                && let Some(specimen) = load_preset(name)
+                            {
+                                // Keep this plant's seed so swapping species doesn't reshuffle it
+                                let seed = plant.seed;
+                                *plant = specimen.to_plant().with_seed(seed);
+                            }
+                        }
+                    });
+                ui.separator();
+
+                ui.label("Adjust step size:");
+                ui.add(
+                    egui::Slider::new(&mut plant.step_size, 1.0..=50.0)
+                        .text("Step Size")
+                );
+
+                ui.label("Root Thickness:");
+                ui.add(
+                    egui::Slider::new(&mut plant.root_thickness, 1.0..=10.0)
+                        .text("Thickness")
+                );
 
-            for (i, rule) in plant.lsystem.rules.iter_mut().enumerate() {
                 ui.horizontal(|ui| {
-                    let mut pred_char = rule.predecessor.to_string();
-                    let mut succ_string = rule.successor.clone();
+                    ui.label("Thickness model:");
+                    if ui.radio(plant.thickness == ThicknessModel::Decay, "Decay (!)").clicked() {
+                        plant.thickness = ThicknessModel::Decay;
+                    }
+                    if ui.radio(matches!(plant.thickness, ThicknessModel::Pipe { .. }), "Pipe model").clicked()
+                        && plant.thickness == ThicknessModel::Decay
+                    {
+                        plant.thickness = ThicknessModel::Pipe { exponent: 2.0, tip_radius: 0.3 };
+                    }
+                });
+                match &mut plant.thickness {
+                    ThicknessModel::Decay => {
+                        ui.add(
+                            egui::Slider::new(&mut plant.lsystem.thickness_decay, 0.5..=1.0)
+                                .text("Decay per !")
+                        );
+                    }
+                    ThicknessModel::Pipe { exponent, tip_radius } => {
+                        ui.add(egui::Slider::new(exponent, 1.0..=4.0).text("Exponent (2 = da Vinci)"));
+                        ui.add(egui::Slider::new(tip_radius, 0.05..=5.0).text("Tip radius"));
+                    }
+                }
+
+                ui.label("Max Iterations:");
+                ui.add(
+                    egui::Slider::new(&mut plant.max_iterations, 1..=10)
+                        .text("Max Iterations")
+                );
+
+                ui.label("Turn Angle:");
+                ui.add(
+                    egui::Slider::new(&mut plant.lsystem.angle, 0.0..=180.0)
+                        .text("Angle (°)")
+                );
+
+                let shared = plant.lsystem.angle;
+                axis_angle_ui(ui, "Yaw (\\ /)", &mut plant.lsystem.yaw, shared);
+                axis_angle_ui(ui, "Pitch (& ^)", &mut plant.lsystem.pitch, shared);
+                axis_angle_ui(ui, "Roll (+ -)", &mut plant.lsystem.roll, shared);
+
+                ui.label("Axiom:");
+                ui.text_edit_singleline(&mut plant.lsystem.axiom);
 
-                    ui.text_edit_singleline(&mut pred_char);
-                    ui.label("->");
-                    ui.text_edit_singleline(&mut succ_string);
+                ui.separator();
+                ui.label("Rules (format: F -> F+F-F, optional condition on ?P / ?E such as y > 40):");
 
-                    if let Some(ch) = pred_char.chars().next() {
-                        rule.predecessor = ch;
+                let mut remove_index: Option<usize> = None;
+
+                for (i, rule) in plant.lsystem.rules.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        let mut pred_char = rule.predecessor.to_string();
+                        let mut succ_string = rule.successor.clone();
+
+                        if ui.text_edit_singleline(&mut pred_char).changed()
+                            && let Some(ch) = pred_char.chars().next()
+                        {
+                            rule.predecessor = ch;
+                        }
+                        ui.label("->");
+                        if ui.text_edit_singleline(&mut succ_string).changed() {
+                            rule.successor = succ_string;
+                        }
+
+                        let mut condition = rule.condition.source().to_string();
+                        if ui.add(egui::TextEdit::singleline(&mut condition).hint_text("condition").desired_width(80.0)).changed() {
+                            rule.condition = Condition::new(&condition);
+                        }
+                        if let Some(error) = rule.condition.error() {
+                            ui.colored_label(egui::Color32::RED, "⚠").on_hover_text(error);
+                        }
+
+                        if ui.button("X").clicked() {
+                            remove_index = Some(i);
+                        }
+                    });
+                }
+
+                // Remove rule if requested
+                if let Some(i) = remove_index {
+                    plant.lsystem.rules.remove(i);
+                }
+
+                // Add new rule
+                if ui.button("Add Rule").clicked() {
+                    plant.lsystem.rules.push(Rule::new('X', ""));
+                }
+                ui.separator();
+
+                egui::CollapsingHeader::new("Level of detail").show(ui, |ui| {
+                    let lod = &mut plant.lod;
+                    ui.checkbox(&mut lod.enabled, "Switch LODs by camera distance");
+                    ui.add(egui::Slider::new(&mut lod.distances[0], 10.0..=5000.0).logarithmic(true).text("Reduced from"));
+                    ui.add(egui::Slider::new(&mut lod.distances[1], 10.0..=5000.0).logarithmic(true).text("Culled from"));
+                    ui.add(egui::Slider::new(&mut lod.distances[2], 10.0..=5000.0).logarithmic(true).text("Billboard from"));
+                    ui.add(egui::Slider::new(&mut lod.full_sides, 3..=16).text("Tube sides (full)"));
+                    ui.add(egui::Slider::new(&mut lod.low_sides, 3..=16).text("Tube sides (reduced)"));
+                    ui.add(egui::Slider::new(&mut lod.merge_length, 0.0..=100.0).text("Merge segments up to"));
+                    ui.add(egui::Slider::new(&mut lod.cull_thickness, 0.0..=5.0).text("Cull thinner than"));
+                    ui.add(egui::Slider::new(&mut lod.impostor_views, 1..=32).text("Impostor views"));
+                    ui.add(egui::Slider::new(&mut lod.impostor_resolution, 16..=512).logarithmic(true).text("Impostor resolution"));
+
+                    // Keep the switch distances increasing
+                    lod.distances[1] = lod.distances[1].max(lod.distances[0]);
+                    lod.distances[2] = lod.distances[2].max(lod.distances[1]);
+                });
+                egui::CollapsingHeader::new("Generator").show(ui, |ui| generator_ui(ui, &mut plant.generator));
+                egui::CollapsingHeader::new("Environment").show(ui, |ui| environment_ui(ui, &mut plant.environment));
+                egui::CollapsingHeader::new("Skeleton").show(ui, |ui| {
+                    ui.checkbox(&mut plant.rig.enabled, "Rig as a joint hierarchy (skinned)");
+                    ui.add(egui::Slider::new(&mut plant.rig.segments_per_joint, 1..=32).text("Segments per joint"));
+                });
+                ui.separator();
+
+                ui.label("Timeline:");
+                let mut iteration = plant.iteration;
+                if ui.add(egui::Slider::new(&mut iteration, 0..=plant.max_iterations).text("Iteration")).changed() {
+                    // Manual stepping pauses playback so the plant stays where it was put
+                    plant.playing = false;
+                    plant.go_to(iteration);
+                }
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(plant.iteration > 0, egui::Button::new("⏴ Back")).clicked() {
+                        plant.playing = false;
+                        let back = plant.iteration - 1;
+                        plant.go_to(back);
                     }
-                    rule.successor = succ_string;
+                    let label = if plant.playing { "⏸ Pause" } else { "⏵ Play" };
+                    if ui.button(label).clicked() {
+                        plant.playing = !plant.playing;
+                    }
+                    if ui.add_enabled(!plant.finished(), egui::Button::new("Forward ⏵")).clicked() {
+                        plant.playing = false;
+                        plant.step();
+                    }
+                    if ui.button("Reset Plant").clicked() {
+                        plant.reset();
+                    }
+                });
+
+            });
+        }
+    });
+}
+
+/// L-system or space colonization, and the colonization parameters
+fn generator_ui(ui: &mut egui::Ui, generator: &mut Generator) {
+    ui.horizontal(|ui| {
+        if ui.radio(*generator == Generator::LSystem, "L-system").clicked() {
+            *generator = Generator::LSystem;
+        }
+        if ui.radio(matches!(generator, Generator::Colonization(_)), "Space colonization").clicked()
+            && *generator == Generator::LSystem
+        {
+            *generator = Generator::Colonization(ColonizationSettings::default());
+        }
+    });
+    let Generator::Colonization(settings) = generator else { return };
 
-                    if ui.button("X").clicked() {
-                        remove_index = Some(i);
+    ui.horizontal(|ui| {
+        ui.label("Attractors in:");
+        if ui.radio(matches!(settings.volume, AttractorVolume::Sphere { .. }), "Sphere").clicked() {
+            settings.volume = AttractorVolume::Sphere { center: Vec3::new(0.0, 120.0, 0.0), radius: 60.0 };
+        }
+        if ui.radio(matches!(settings.volume, AttractorVolume::Ellipsoid { .. }), "Ellipsoid").clicked() {
+            settings.volume = AttractorVolume::Ellipsoid { center: Vec3::new(0.0, 120.0, 0.0), radii: Vec3::new(70.0, 50.0, 70.0) };
+        }
+        if ui.radio(matches!(settings.volume, AttractorVolume::Mesh { .. }), "Mesh").clicked() {
+            settings.volume = AttractorVolume::Mesh { path: String::new() };
+        }
+    });
+    match &mut settings.volume {
+        AttractorVolume::Sphere { center, radius } => {
+            vec3_ui(ui, "Centre", center, f32::MIN);
+            ui.add(egui::Slider::new(radius, 1.0..=300.0).text("Radius"));
+        }
+        AttractorVolume::Ellipsoid { center, radii } => {
+            vec3_ui(ui, "Centre", center, f32::MIN);
+            vec3_ui(ui, "Radii", radii, 1.0);
+        }
+        AttractorVolume::Mesh { path } => {
+            ui.horizontal(|ui| {
+                ui.label("Asset:");
+                // Edited in a scratch copy and committed on Enter or losing focus, so the
+                // asset server isn't asked for every prefix typed on the way
+                let id = ui.make_persistent_id("volume_mesh_path");
+                let mut text = ui.data(|d| d.get_temp::<String>(id)).unwrap_or_else(|| path.clone());
+                let response = ui.text_edit_singleline(&mut text)
+                    .on_hover_text("Closed mesh, e.g. crowns/oak.glb#Mesh0/Primitive0");
+                if response.lost_focus() {
+                    if *path != text {
+                        *path = text;
                     }
+                    ui.data_mut(|d| d.remove::<String>(id));
+                } else if response.has_focus() {
+                    ui.data_mut(|d| d.insert_temp(id, text));
+                } else {
+                    ui.data_mut(|d| d.remove::<String>(id));
+                }
+            });
+        }
+    }
+    ui.add(egui::Slider::new(&mut settings.attractors, 10..=5000).logarithmic(true).text("Attractors"));
+    ui.add(egui::Slider::new(&mut settings.influence_radius, 1.0..=200.0).text("Influence radius"));
+    ui.add(egui::Slider::new(&mut settings.kill_distance, 0.5..=100.0).text("Kill distance"));
+    ui.add(egui::Slider::new(&mut settings.step, 0.5..=50.0).text("Step"));
+    ui.add(egui::Slider::new(&mut settings.steps_per_iteration, 1..=50).text("Steps per iteration"));
+    ui.checkbox(&mut settings.leaves, "Leaves on branch tips");
+}
+
+/// Which environment answers `?E` modules, and its parameters
+fn environment_ui(ui: &mut egui::Ui, environment: &mut EnvironmentKind) {
+    ui.horizontal(|ui| {
+        if ui.radio(*environment == EnvironmentKind::None, "None").clicked() {
+            *environment = EnvironmentKind::None;
+        }
+        if ui.radio(matches!(environment, EnvironmentKind::Ground { .. }), "Ground").clicked() {
+            *environment = EnvironmentKind::Ground { height: 0.0 };
+        }
+        if ui.radio(matches!(environment, EnvironmentKind::Topiary { .. }), "Topiary").clicked() {
+            *environment = EnvironmentKind::Topiary { center: Vec3::new(0.0, 60.0, 0.0), radii: Vec3::new(40.0, 50.0, 40.0) };
+        }
+        if ui.radio(matches!(environment, EnvironmentKind::Light { .. }), "Light").clicked() {
+            *environment = EnvironmentKind::Light { direction: Vec3::Y, ambient: 0.2, extinction: 0.02, top: 100.0 };
+        }
+        if ui.radio(*environment == EnvironmentKind::Competition, "Light competition").clicked() {
+            *environment = EnvironmentKind::Competition;
+        }
+    });
+    match environment {
+        EnvironmentKind::None => {}
+        EnvironmentKind::Competition => {
+            ui.label("Buds ask ?E(exposure, x, z) of the shared light grid, shaded by every plant's leaves");
+        }
+        EnvironmentKind::Ground { height } => {
+            ui.add(egui::Slider::new(height, -100.0..=100.0).text("Ground height"));
+        }
+        EnvironmentKind::Topiary { center, radii } => {
+            vec3_ui(ui, "Centre", center, f32::MIN);
+            vec3_ui(ui, "Radii", radii, 1.0);
+        }
+        EnvironmentKind::Light { direction, ambient, extinction, top } => {
+            vec3_ui(ui, "Towards light", direction, f32::MIN);
+            ui.add(egui::Slider::new(ambient, 0.0..=1.0).text("Ambient"));
+            ui.add(egui::Slider::new(extinction, 0.0..=0.2).text("Extinction per unit"));
+            ui.add(egui::Slider::new(top, 0.0..=500.0).text("Canopy top"));
+        }
+    }
+}
+
+fn vec3_ui(ui: &mut egui::Ui, label: &str, value: &mut Vec3, min: f32) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        for axis in [&mut value.x, &mut value.y, &mut value.z] {
+            ui.add(egui::DragValue::new(axis).speed(0.5).range(min..=f32::MAX));
+        }
+    });
+}
+
+/// Optional per-axis angle override plus its jitter
+fn axis_angle_ui(ui: &mut egui::Ui, label: &str, axis: &mut AxisAngle, shared: f32) {
+    ui.horizontal(|ui| {
+        let mut separate = axis.angle.is_some();
+        ui.checkbox(&mut separate, label);
+        match (separate, axis.angle) {
+            (true, None) => axis.angle = Some(shared),
+            (false, Some(_)) => axis.angle = None,
+            _ => {}
+        }
+
+        if let Some(angle) = &mut axis.angle {
+            ui.add(egui::Slider::new(angle, 0.0..=180.0).text("°"));
+        }
+    });
+    ui.add(egui::Slider::new(&mut axis.jitter, 0.0..=45.0).text("± jitter (°)"));
+}
+
+pub fn palette_ui(mut contexts: EguiContexts, selected: Res<SelectedPlant>, mut query: Query<&mut Plant>) {
+    egui::Window::new("Palette Editor").show(contexts.ctx_mut().unwrap(), |ui| {
+        if let Some(mut plant) = selected.0.and_then(|e| query.get_mut(e).ok()) {
+            edit_plant(&mut plant, |plant| {
+                ui.horizontal(|ui| {
+                    ui.label("Colour by:");
+                    ui.selectable_value(&mut plant.color_mode, ColorMode::Index, "Index (')");
+                    ui.selectable_value(&mut plant.color_mode, ColorMode::Depth, "Depth");
+                    ui.selectable_value(&mut plant.color_mode, ColorMode::Iteration, "Iteration");
+                    ui.selectable_value(&mut plant.color_mode, ColorMode::Distance, "Distance");
                 });
+
+                gradient_preview(ui, &plant.palette);
+                ui.label("Edit Plant Palette (position, RGBA):");
+
+                let mut remove_index: Option<usize> = None;
+                let palette_len = plant.palette.stops.len(); // capture length once here
+
+                // Iterate by index to avoid borrowing the whole palette mutably in the closure
+                for i in 0..palette_len {
+                    // Get mutable reference to the i-th stop
+                    let stop = &mut plant.palette.stops[i];
+
+                    ui.horizontal(|ui| {
+                        let [mut r, mut g, mut b, mut a] = stop.color.to_srgba().to_u8_array();
+
+                        ui.add(egui::DragValue::new(&mut stop.position).speed(0.01).range(0.0..=1.0));
+                        ui.label("R:");
+                        let mut edited = ui.add(egui::DragValue::new(&mut r).range(0..=255)).changed();
+                        ui.label("G:");
+                        edited |= ui.add(egui::DragValue::new(&mut g).range(0..=255)).changed();
+                        ui.label("B:");
+                        edited |= ui.add(egui::DragValue::new(&mut b).range(0..=255)).changed();
+                        ui.label("A:");
+                        edited |= ui.add(egui::DragValue::new(&mut a).range(0..=255)).changed();
+
+                        // Write back into the color
+                        if edited {
+                            stop.color = Color::srgba_u8(r, g, b, a);
+                        }
+
+                        // Only mark for removal; don't mutate the palette here
+                        if ui.button("X").clicked() && palette_len > 1 {
+                            remove_index = Some(i);
+                        }
+                    });
+                }
+
+                // Remove outside the loop
+                if let Some(i) = remove_index {
+                    plant.palette.stops.remove(i);
+                }
+
+                if ui.button("Add Color").clicked() {
+                    plant.palette.stops.push(ColorStop { position: 1.0, color: Color::srgba_u8(128, 64, 0, 255) });
+                }
+
+                ui.separator();
+                ui.label("Tip: In Index mode, palette cycles are applied with the `'` operator in the L-system.");
+                ui.label("Other modes blend the stops in Oklab.");
+            });
+        }
+    });
+}
+
+/// Strip showing the palette sampled as a gradient
+fn gradient_preview(ui: &mut egui::Ui, palette: &Palette) {
+    const STEPS: usize = 64;
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 16.0), egui::Sense::hover());
+    let width = rect.width() / STEPS as f32;
+
+    for i in 0..STEPS {
+        let [r, g, b, a] = palette.sample(i as f32 / (STEPS - 1) as f32).to_srgba().to_u8_array();
+        let x = rect.left() + i as f32 * width;
+        ui.painter().rect_filled(
+            egui::Rect::from_min_size(egui::pos2(x, rect.top()), egui::vec2(width + 0.5, rect.height())),
+            0.0,
+            egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+        );
+    }
+}
+
+pub fn growth_ui(
+    mut contexts: EguiContexts,
+    mut clock: ResMut<GrowthClock>,
+    selected: Res<SelectedPlant>,
+    mut timers: Query<&mut GrowthTimer>,
+) {
+    egui::Window::new("Growth").show(contexts.ctx_mut().unwrap(), |ui| {
+        ui.horizontal(|ui| {
+            let label = if clock.playing { "⏸ Pause all" } else { "⏵ Play all" };
+            if ui.button(label).clicked() {
+                clock.playing = !clock.playing;
+            }
+            if ui.button("Step once").clicked() {
+                clock.step_once = true;
             }
+        });
+        ui.add(egui::Slider::new(&mut clock.rate, 0.1..=20.0).logarithmic(true).text("Iterations per second"));
+        ui.add(egui::Slider::new(&mut clock.speed, 0.1..=10.0).logarithmic(true).text("Speed"));
+
+        if let Some(mut timer) = selected.0.and_then(|e| timers.get_mut(e).ok()) {
+            ui.separator();
+            ui.add(egui::Slider::new(&mut timer.start_delay, 0.0..=30.0).text("Selected plant start delay (s)"));
+        }
+    });
+}
+
+pub fn stats_ui(mut contexts: EguiContexts, selected: Res<SelectedPlant>, query: Query<(&Plant, &PlantStats)>) {
+    egui::Window::new("Statistics").show(contexts.ctx_mut().unwrap(), |ui| {
+        let Some((plant, stats)) = selected.0.and_then(|e| query.get(e).ok()) else {
+            ui.label("No plant selected");
+            return;
+        };
 
-            // Remove rule if requested
-            if let Some(i) = remove_index {
-                plant.lsystem.rules.remove(i);
+        egui::Grid::new("stats_counts").num_columns(2).show(ui, |ui| {
+            let mut row = |label: &str, value: String| {
+                ui.label(label);
+                ui.label(value);
+                ui.end_row();
+            };
+            row("Iteration", format!("{} / {}", plant.iteration, plant.max_iterations));
+            row("String length", stats.growth.last().copied().unwrap_or(0).to_string());
+            row("Segments", stats.segments.to_string());
+            row("Leaves", stats.leaves.to_string());
+            row("Vertices", stats.vertices.to_string());
+            row("Triangles", stats.triangles.to_string());
+            row("Max depth", format!("{} (order {})", stats.max_depth, stats.max_order));
+            row("Height", format!("{:.1}", stats.height()));
+            if let Some((min, max)) = stats.bounds {
+                row("Bounds", format!("{:.0} … {:.0}", min, max));
             }
+            row("Step", format!("{:.2?}", stats.step_time));
+            row("Interpretation", format!("{:.2?}", stats.interpret_time));
+            row("Mesh building", format!("{:.2?}", stats.mesh_time));
+        });
 
-            // Add new rule
-            if ui.button("Add Rule").clicked() {
-                plant.lsystem.rules.push(Rule::new('X', ""));
+        ui.separator();
+        ui.label("String length per iteration:");
+        growth_chart(ui, stats.growth_start, &stats.growth);
+
+        egui::CollapsingHeader::new("Symbol histogram").show(ui, |ui| {
+            let total = stats.growth.last().copied().unwrap_or(0).max(1);
+            for &(symbol, count) in &stats.symbols {
+                let fraction = count as f32 / total as f32;
+                ui.add(egui::ProgressBar::new(fraction).text(format!("{symbol}  {count}  ({:.1}%)", fraction * 100.0)));
             }
-            ui.separator();
+        });
+    });
+}
+
+/// Log-scale line chart, since strings usually grow exponentially. `growth[0]` is iteration
+/// `start`; the x axis always begins at the axiom.
+fn growth_chart(ui: &mut egui::Ui, start: usize, growth: &[usize]) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 60.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY), egui::StrokeKind::Inside);
+    if growth.len() < 2 {
+        return;
+    }
+
+    let max = growth.iter().map(|&n| (n.max(1) as f32).ln()).fold(0.0, f32::max).max(f32::EPSILON);
+    let points: Vec<egui::Pos2> = growth.iter().enumerate().map(|(i, &n)| {
+        let x = rect.left() + (start + i) as f32 / (start + growth.len() - 1) as f32 * rect.width();
+        let y = rect.bottom() - (n.max(1) as f32).ln() / max * rect.height();
+        egui::pos2(x, y)
+    }).collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, egui::Color32::LIGHT_GREEN)));
+}
+
+pub fn ecosystem_ui(mut contexts: EguiContexts, mut settings: ResMut<EcosystemSettings>) {
+    egui::Window::new("Ecosystem").show(contexts.ctx_mut().unwrap(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("World seed:");
+            ui.add(egui::DragValue::new(&mut settings.world_seed));
+            if ui.button("🎲").on_hover_text("New world seed").clicked() {
+                settings.world_seed = rand::random();
+                settings.respawn = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Region:");
+            ui.add(egui::DragValue::new(&mut settings.region_min.x).prefix("x "));
+            ui.add(egui::DragValue::new(&mut settings.region_max.x).prefix("to "));
+            ui.add(egui::DragValue::new(&mut settings.region_min.y).prefix("z "));
+            ui.add(egui::DragValue::new(&mut settings.region_max.y).prefix("to "));
+        });
+        // Keep the region the right way round whichever end was dragged past the other
+        let (min, max) = (settings.region_min.min(settings.region_max), settings.region_min.max(settings.region_max));
+        if (min, max) != (settings.region_min, settings.region_max) {
+            (settings.region_min, settings.region_max) = (min, max);
+        }
+        ui.add(egui::Slider::new(&mut settings.min_spacing, 10.0..=500.0).logarithmic(true).text("Minimum spacing"));
+
+        egui::CollapsingHeader::new("Terrain").show(ui, |ui| {
+            let terrain = &mut settings.terrain;
+            ui.horizontal(|ui| {
+                if ui.radio(terrain.source == HeightSource::Flat, "Flat").clicked() {
+                    terrain.source = HeightSource::Flat;
+                }
+                if ui.radio(matches!(terrain.source, HeightSource::Noise { .. }), "Noise").clicked() {
+                    terrain.source = HeightSource::Noise { frequency: 2.0, octaves: 4 };
+                }
+                if ui.radio(matches!(terrain.source, HeightSource::Image { .. }), "Heightmap").clicked() {
+                    terrain.source = HeightSource::Image { path: "heightmap.png".to_string() };
+                }
+            });
+            match &mut terrain.source {
+                HeightSource::Flat => {}
+                HeightSource::Noise { frequency, octaves } => {
+                    ui.add(egui::Slider::new(frequency, 0.1..=20.0).logarithmic(true).text("Hills per 1000 units"));
+                    ui.add(egui::Slider::new(octaves, 1..=8).text("Octaves"));
+                }
+                HeightSource::Image { path } => {
+                    ui.horizontal(|ui| {
+                        ui.label("16-bit PNG:");
+                        ui.text_edit_singleline(path);
+                    });
+                }
+            }
+            ui.add(egui::Slider::new(&mut terrain.size, 100.0..=5000.0).logarithmic(true).text("Size"));
+            ui.add(egui::Slider::new(&mut terrain.base, -1000.0..=0.0).text("Base height"));
+            ui.add(egui::Slider::new(&mut terrain.relief, 0.0..=1000.0).text("Relief"));
+            ui.add(egui::Slider::new(&mut terrain.resolution, 8..=512).logarithmic(true).text("Resolution"));
+            ui.checkbox(&mut settings.align_to_normal, "Align plants to the ground normal");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Density:");
+            if ui.radio(settings.density == DensityMap::Uniform, "Uniform").clicked() {
+                settings.density = DensityMap::Uniform;
+            }
+            if ui.radio(matches!(settings.density, DensityMap::Noise { .. }), "Noise").clicked() {
+                settings.density = DensityMap::Noise { frequency: 3.0, octaves: 3 };
+            }
+            if ui.radio(matches!(settings.density, DensityMap::Image { .. }), "Image").clicked() {
+                settings.density = DensityMap::Image { path: "density.png".to_string() };
+            }
+        });
+        match &mut settings.density {
+            DensityMap::Uniform => {}
+            DensityMap::Noise { frequency, octaves } => {
+                ui.add(egui::Slider::new(frequency, 0.1..=20.0).logarithmic(true).text("Patches per 1000 units"));
+                ui.add(egui::Slider::new(octaves, 1..=8).text("Octaves"));
+            }
+            DensityMap::Image { path } => {
+                ui.horizontal(|ui| {
+                    ui.label("Grayscale image:");
+                    ui.text_edit_singleline(path);
+                });
+            }
+        }
+
+        ui.separator();
+        ui.label("Species mix:");
+        let mut remove = None;
+        for (i, species) in settings.species.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt(("species", i))
+                    .selected_text(species.preset.as_str())
+                    .show_ui(ui, |ui| {
+                        for (name, _) in PRESETS {
+                            ui.selectable_value(&mut species.preset, name.to_string(), *name);
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut species.weight, 0.0..=10.0).text("Weight"));
+                if ui.button("X").clicked() {
+                    remove = Some(i);
+                }
+            });
+            ui.horizontal(|ui| {
+                let mask = &mut species.mask;
+                ui.label("    altitude");
+                ui.add(egui::DragValue::new(&mut mask.altitude.x).speed(0.01).range(0.0..=1.0));
+                ui.add(egui::DragValue::new(&mut mask.altitude.y).speed(0.01).range(0.0..=1.0));
+                ui.add(egui::Slider::new(&mut mask.max_slope, 0.0..=90.0).text("Max slope (°)"));
+            });
+        }
+        if let Some(i) = remove {
+            settings.species.remove(i);
+        }
+        if ui.button("Add species").clicked() {
+            settings.species.push(SpeciesWeight { preset: PRESETS[0].0.to_string(), weight: 1.0, mask: SpeciesMask::default(), life: LifeCycle::default() });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Scale:");
+            ui.add(egui::DragValue::new(&mut settings.scale.x).speed(0.01).range(0.05..=10.0));
+            ui.add(egui::DragValue::new(&mut settings.scale.y).speed(0.01).range(0.05..=10.0));
+        });
+        ui.checkbox(&mut settings.random_yaw, "Random rotation");
+        ui.add(egui::Slider::new(&mut settings.max_start_delay, 0.0..=30.0).text("Start delay spread (s)"));
+
+        if ui.button("Regenerate").clicked() {
+            settings.respawn = true;
+        }
+    });
+}
 
-            if ui.button("Reset Plant").clicked() {
-                plant.reset();
+pub fn simulation_ui(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<SimulationSettings>,
+    mut ecosystem: ResMut<EcosystemSettings>,
+    simulation: Option<Res<Simulation>>,
+) {
+    egui::Window::new("Simulation").show(contexts.ctx_mut().unwrap(), |ui| {
+        ui.horizontal(|ui| {
+            let label = if simulation.is_some() { "⟲ Restart" } else { "⏵ Start" };
+            if ui.button(label).on_hover_text("Start over from the ecosystem's scatter").clicked() {
+                settings.restart = true;
+                settings.running = true;
             }
+            if simulation.is_some() {
+                let label = if settings.running { "⏸ Pause" } else { "⏵ Play" };
+                if ui.button(label).clicked() {
+                    settings.running = !settings.running;
+                }
+                if ui.button("Step generation").clicked() {
+                    settings.step_once = true;
+                }
+            }
+        });
+        ui.add(egui::Slider::new(&mut settings.generations_per_second, 0.1..=10.0).logarithmic(true).text("Generations per second"));
+        ui.add(egui::Slider::new(&mut settings.competition, 0.0..=2.0).text("Competition"));
+        ui.add(egui::Slider::new(&mut settings.max_population, 10..=2000).logarithmic(true).text("Max population"));
 
+        if let Some(simulation) = &simulation {
             ui.separator();
-            ui.label("Current String:");
-            ui.label(plant.current_string.clone());
+            let total: usize = simulation.census.last().map_or(0, |counts| counts.iter().sum());
+            ui.label(format!("Generation {}: {total} plants", simulation.generation));
+            let names: Vec<&str> = simulation.ecosystem.species.iter().map(|s| s.preset.as_str()).collect();
+            population_chart(ui, &names, &simulation.census);
         }
+
+        egui::CollapsingHeader::new("Life cycles (apply on restart)").show(ui, |ui| {
+            for (i, species) in ecosystem.species.iter_mut().enumerate() {
+                let life = &mut species.life;
+                ui.label(species.preset.as_str());
+                ui.horizontal(|ui| {
+                    ui.label("    flower");
+                    let mut flower = life.flower.to_string();
+                    if ui.add(egui::TextEdit::singleline(&mut flower).desired_width(16.0)).changed()
+                        && let Some(c) = flower.chars().last()
+                    {
+                        life.flower = c;
+                    }
+                    ui.add(egui::DragValue::new(&mut life.seeds_per_flower).speed(0.001).range(0.0..=10.0).prefix("seeds "));
+                    ui.add(egui::DragValue::new(&mut life.lifespan).range(1..=1000).prefix("lifespan "));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("    dispersal");
+                    egui::ComboBox::from_id_salt(("dispersal", i))
+                        .selected_text(match life.dispersal {
+                            DispersalKernel::Gaussian { .. } => "Gaussian",
+                            DispersalKernel::Exponential { .. } => "Exponential",
+                        })
+                        .show_ui(ui, |ui| {
+                            if ui.selectable_label(matches!(life.dispersal, DispersalKernel::Gaussian { .. }), "Gaussian").clicked() {
+                                life.dispersal = DispersalKernel::Gaussian { sigma: 150.0 };
+                            }
+                            if ui.selectable_label(matches!(life.dispersal, DispersalKernel::Exponential { .. }), "Exponential").clicked() {
+                                life.dispersal = DispersalKernel::Exponential { mean: 150.0 };
+                            }
+                        });
+                    match &mut life.dispersal {
+                        DispersalKernel::Gaussian { sigma } => ui.add(egui::DragValue::new(sigma).range(1.0..=5000.0).prefix("σ ")),
+                        DispersalKernel::Exponential { mean } => ui.add(egui::DragValue::new(mean).range(1.0..=5000.0).prefix("mean ")),
+                    };
+                });
+            }
+        });
     });
 }
 
-pub fn palette_ui(mut contexts: EguiContexts, mut query: Query<&mut Plant>) {
-    egui::Window::new("Palette Editor").show(contexts.ctx_mut().unwrap(), |ui| {
-        for mut plant in query.iter_mut() {
-            ui.label("Edit Plant Palette:");
+/// One line per species over the generations, with a legend
+fn population_chart(ui: &mut egui::Ui, names: &[&str], census: &[Vec<usize>]) {
+    const COLORS: [egui::Color32; 6] = [
+        egui::Color32::LIGHT_GREEN,
+        egui::Color32::LIGHT_BLUE,
+        egui::Color32::GOLD,
+        egui::Color32::LIGHT_RED,
+        egui::Color32::KHAKI,
+        egui::Color32::WHITE,
+    ];
+    ui.horizontal_wrapped(|ui| {
+        for (i, name) in names.iter().enumerate() {
+            let count = census.last().and_then(|counts| counts.get(i)).copied().unwrap_or(0);
+            ui.colored_label(COLORS[i % COLORS.len()], format!("■ {name} {count}"));
+        }
+    });
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 100.0), egui::Sense::hover());
+    let painter = ui.painter();
+    painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY), egui::StrokeKind::Inside);
+    if census.len() < 2 {
+        return;
+    }
 
-            let mut remove_index: Option<usize> = None;
-            let palette_len = plant.palette.len(); // capture length once here
+    let max = census.iter().flatten().copied().max().unwrap_or(0).max(1) as f32;
+    for (i, color) in (0..names.len()).zip(COLORS.iter().cycle()) {
+        let points: Vec<egui::Pos2> = census.iter().enumerate().map(|(generation, counts)| {
+            let x = rect.left() + generation as f32 / (census.len() - 1) as f32 * rect.width();
+            let y = rect.bottom() - counts.get(i).copied().unwrap_or(0) as f32 / max * rect.height();
+            egui::pos2(x, y)
+        }).collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
+    }
+}
+
+pub fn breeding_ui(
+    mut contexts: EguiContexts,
+    mut breeding: ResMut<Breeding>,
+    mut selected: ResMut<SelectedPlant>,
+    plants: Query<&Plant>,
+    offspring: Query<(Entity, &Offspring)>,
+) {
+    egui::Window::new("Breeding").show(contexts.ctx_mut().unwrap(), |ui| {
+        let founder = selected.0.and_then(|e| plants.get(e).ok());
+        if !breeding.is_active() {
+            if ui.add_enabled(founder.is_some(), egui::Button::new("Breed from selected plant")).clicked()
+                && let Some(plant) = founder
+            {
+                breeding.start(Specimen::from_plant(plant));
+            }
+        } else {
+            ui.label(format!("Generation {}: pick favourites, then breed the next generation", breeding.generation));
+            let columns = (breeding.brood.len() as f32).sqrt().ceil().max(1.0) as usize;
+            let brood = breeding.brood.clone();
+            egui::Grid::new("brood").num_columns(columns).show(ui, |ui| {
+                for (i, &id) in brood.iter().enumerate() {
+                    let genome = &breeding.lineage[id as usize];
+                    let parents: Vec<String> = genome.parents.iter().map(|p| format!("#{p}")).collect();
+                    let hover = format!("parents {}\nseed {}", parents.join(" × "), genome.seed);
+                    let favourite = breeding.favourites.contains(&id);
+                    ui.horizontal(|ui| {
+                        let label = if favourite { format!("★ #{id}") } else { format!("#{id}") };
+                        if ui.selectable_label(favourite, label).on_hover_text(hover).clicked() {
+                            breeding.toggle_favourite(id);
+                        }
+                        if ui.small_button("🔍").on_hover_text("Select to inspect or save").clicked() {
+                            selected.0 = offspring.iter().find(|(_, o)| o.0 == id).map(|(e, _)| e);
+                        }
+                    });
+                    if (i + 1) % columns == 0 {
+                        ui.end_row();
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!breeding.favourites.is_empty(), egui::Button::new("Next generation")).clicked() {
+                    breeding.next_generation();
+                }
+                if ui.button("Stop breeding").clicked() {
+                    breeding.stop();
+                }
+            });
+        }
 
-            // Iterate by index to avoid borrowing the whole palette mutably in the closure
-            for i in 0..palette_len {
-                // Get mutable reference to the i-th color
-                let color = &mut plant.palette[i];
+        ui.separator();
+        ui.add(egui::Slider::new(&mut breeding.brood_size, 1..=25).text("Offspring per generation"));
+        ui.add(egui::Slider::new(&mut breeding.spacing, 50.0..=1000.0).logarithmic(true).text("Grid spacing"));
+        let rates = &mut breeding.rates;
+        ui.add(egui::Slider::new(&mut rates.symbol, 0.0..=1.0).text("Successor edits per rule"));
+        ui.add(egui::Slider::new(&mut rates.angle, 0.0..=45.0).text("Angle change (°)"));
+        ui.add(egui::Slider::new(&mut rates.probability, 0.0..=1.0).text("Probability tweaks"));
 
+        if breeding.is_active() {
+            egui::CollapsingHeader::new("Lineage").show(ui, |ui| {
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for genome in &breeding.lineage {
+                        let parents: Vec<String> = genome.parents.iter().map(|p| format!("#{p}")).collect();
+                        ui.label(format!(
+                            "#{} gen {} ← {}  seed {}",
+                            genome.id,
+                            genome.generation,
+                            if parents.is_empty() { "founder".to_string() } else { parents.join(" × ") },
+                            genome.seed,
+                        ));
+                    }
+                });
                 ui.horizontal(|ui| {
-                    let mut r = (color.to_srgba().red * 255.0) as u8;
-                    let mut g = (color.to_srgba().green * 255.0) as u8;
-                    let mut b = (color.to_srgba().blue * 255.0) as u8;
-
-                    ui.label("R:");
-                    ui.add(egui::DragValue::new(&mut r).range(0..=255));
-                    ui.label("G:");
-                    ui.add(egui::DragValue::new(&mut g).range(0..=255));
-                    ui.label("B:");
-                    ui.add(egui::DragValue::new(&mut b).range(0..=255));
-
-                    // Write back into the color
-                    *color = Color::srgba_u8(r, g, b, 255);
-
-                    // Only mark for removal; don't mutate the palette here
-                    if ui.button("X").clicked() && palette_len > 1 {
-                        remove_index = Some(i);
+                    ui.text_edit_singleline(&mut breeding.lineage_path);
+                    if ui.button("Save lineage").clicked() {
+                        breeding.status = match breeding.save_lineage(Path::new(&breeding.lineage_path)) {
+                            Ok(()) => format!("Saved {}", breeding.lineage_path),
+                            Err(e) => format!("Save failed: {e}"),
+                        };
                     }
                 });
+                if !breeding.status.is_empty() {
+                    ui.label(breeding.status.as_str());
+                }
+            });
+        }
+    });
+}
+
+pub fn wind_ui(mut contexts: EguiContexts, mut wind: ResMut<Wind>) {
+    // Edit a copy so materials are only updated when something actually changes
+    let mut edited = *wind;
+    egui::Window::new("Wind").show(contexts.ctx_mut().unwrap(), |ui| {
+        let mut heading = edited.direction.z.atan2(edited.direction.x).to_degrees();
+        if ui.add(egui::Slider::new(&mut heading, -180.0..=180.0).text("Direction")).changed() {
+            let radians = heading.to_radians();
+            edited.direction = Vec3::new(radians.cos(), 0.0, radians.sin());
+        }
+        ui.add(egui::Slider::new(&mut edited.strength, 0.0..=5.0).text("Strength"));
+        ui.add(egui::Slider::new(&mut edited.gustiness, 0.0..=1.0).text("Gustiness"));
+    });
+    if edited.direction != wind.direction || edited.strength != wind.strength || edited.gustiness != wind.gustiness {
+        *wind = edited;
+    }
+}
+
+pub fn variation_ui(mut contexts: EguiContexts, selected: Res<SelectedPlant>, mut query: Query<&mut Plant>) {
+    egui::Window::new("Variation").show(contexts.ctx_mut().unwrap(), |ui| {
+        if let Some(mut plant) = selected.0.and_then(|e| query.get_mut(e).ok()) {
+            edit_plant(&mut plant, |plant| {
+                ui.horizontal(|ui| {
+                    ui.label("Seed:");
+                    ui.add(egui::DragValue::new(&mut plant.seed));
+                });
+                ui.separator();
+                ui.label("Per-symbol jitter:");
+
+                let mut remove_index: Option<usize> = None;
+
+                for (i, entry) in plant.variation.symbols.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.horizontal(|ui| {
+                            let mut symbol = entry.symbol.to_string();
+                            ui.label("Symbol:");
+                            if ui.text_edit_singleline(&mut symbol).changed()
+                                && let Some(ch) = symbol.chars().next()
+                            {
+                                entry.symbol = ch;
+                            }
+
+                            if ui.button("X").clicked() {
+                                remove_index = Some(i);
+                            }
+                        });
+
+                        jitter_ui(ui, "Step length (×)", &mut entry.step_length);
+                        jitter_ui(ui, "Turn angle (°)", &mut entry.turn_angle);
+                        jitter_ui(ui, "Thickness decay (×)", &mut entry.thickness_decay);
+                        jitter_ui(ui, "Leaf scale (×)", &mut entry.leaf_scale);
+                    });
+                    ui.separator();
+                }
+
+                if let Some(i) = remove_index {
+                    plant.variation.symbols.remove(i);
+                }
+
+                if ui.button("Add Symbol").clicked() {
+                    plant.variation.symbols.push(SymbolVariation::new('F'));
+                }
+            });
+        }
+    });
+}
+
+fn jitter_ui(ui: &mut egui::Ui, label: &str, jitter: &mut Jitter) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(&mut jitter.amount).speed(0.01).range(0.0..=f32::MAX));
+        ui.selectable_value(&mut jitter.distribution, Distribution::Uniform, "Uniform");
+        ui.selectable_value(&mut jitter.distribution, Distribution::Normal, "Normal");
+    });
+}
+
+pub fn material_ui(mut contexts: EguiContexts, selected: Res<SelectedPlant>, mut query: Query<&mut Plant>) {
+    egui::Window::new("Materials").show(contexts.ctx_mut().unwrap(), |ui| {
+        if let Some(mut plant) = selected.0.and_then(|e| query.get_mut(e).ok()) {
+            edit_plant(&mut plant, |plant| {
+                let materials = &mut plant.materials;
+                egui::CollapsingHeader::new("Bark").show(ui, |ui| surface_material_ui(ui, &mut materials.bark));
+                egui::CollapsingHeader::new("Leaves").show(ui, |ui| surface_material_ui(ui, &mut materials.leaf));
+                egui::CollapsingHeader::new("Petals").show(ui, |ui| surface_material_ui(ui, &mut materials.petal));
+                ui.add(
+                    egui::Slider::new(&mut materials.texel_density, 0.01..=1.0)
+                        .logarithmic(true)
+                        .text("Bark UV repeats per unit")
+                );
+
+                ui.separator();
+                ui.label("Tip: `%` makes the following `{ }` polygons in a branch petals.");
+            });
+        }
+    });
+}
+
+fn surface_material_ui(ui: &mut egui::Ui, material: &mut SurfaceMaterial) {
+    ui.horizontal(|ui| {
+        let [mut r, mut g, mut b, mut a] = material.base_color.to_srgba().to_u8_array();
+
+        ui.label("Base R:");
+        let mut edited = ui.add(egui::DragValue::new(&mut r).range(0..=255)).changed();
+        ui.label("G:");
+        edited |= ui.add(egui::DragValue::new(&mut g).range(0..=255)).changed();
+        ui.label("B:");
+        edited |= ui.add(egui::DragValue::new(&mut b).range(0..=255)).changed();
+        ui.label("A:");
+        edited |= ui.add(egui::DragValue::new(&mut a).range(0..=255)).changed();
+
+        if edited {
+            material.base_color = Color::srgba_u8(r, g, b, a);
+        }
+    });
+
+    ui.add(egui::Slider::new(&mut material.perceptual_roughness, 0.089..=1.0).text("Roughness"));
+    ui.add(egui::Slider::new(&mut material.metallic, 0.0..=1.0).text("Metallic"));
+    ui.add(egui::Slider::new(&mut material.reflectance, 0.0..=1.0).text("Reflectance"));
+    ui.add(egui::Slider::new(&mut material.diffuse_transmission, 0.0..=1.0).text("Transmission"));
+    ui.checkbox(&mut material.double_sided, "Double-sided");
+    ui.checkbox(&mut material.alpha_blend, "Alpha blend");
+}
+
+pub fn specimen_ui(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut file: ResMut<SpecimenFile>,
+    mut selected: ResMut<SelectedPlant>,
+    query: Query<&Plant>,
+    meshes: Res<Assets<Mesh>>,
+    volumes: Res<VolumeMeshes>,
+) {
+    egui::Window::new("Specimen").show(contexts.ctx_mut().unwrap(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("File:");
+            ui.text_edit_singleline(&mut file.path);
+        });
+        ui.checkbox(&mut file.replace_selected, "Open replaces selected plant");
+        ui.label("Format follows the extension: .json for JSON, anything else is RON.");
+
+        ui.horizontal(|ui| {
+            if ui.button("Save as…").clicked() {
+                file.status = match selected.0.and_then(|e| query.get(e).ok()) {
+                    Some(plant) => match Specimen::from_plant(plant).save(Path::new(&file.path)) {
+                        Ok(()) => format!("Saved {}", file.path),
+                        Err(e) => format!("Save failed: {e}"),
+                    },
+                    None => "No plant selected".to_string(),
+                };
             }
 
-            // Remove outside the loop
-            if let Some(i) = remove_index {
-                plant.palette.remove(i);
+            if ui.button("Export glTF…").clicked() {
+                let path = Path::new(&file.path).with_extension("glb");
+                file.status = match selected.0.and_then(|e| query.get(e).ok()) {
+                    Some(plant) => match export_glb(plant, volumes.get(plant, &meshes), &path) {
+                        Ok(()) => format!("Exported {}", path.display()),
+                        Err(e) => format!("Export failed: {e}"),
+                    },
+                    None => "No plant selected".to_string(),
+                };
             }
 
-            if ui.button("Add Color").clicked() {
-                plant.palette.push(Color::srgba_u8(128, 64, 0, 255));
+            if ui.button("Open…").clicked() {
+                file.status = match Specimen::load(Path::new(&file.path)) {
+                    Ok(specimen) => {
+                        let plant = specimen.to_plant();
+                        match selected.0.filter(|_| file.replace_selected) {
+                            Some(entity) => {
+                                commands.entity(entity).insert(plant);
+                            }
+                            None => {
+                                let entity = commands.spawn((
+                                    plant,
+                                    Transform::from_translation(Vec3::new(0.0, -200.0, 0.0)),
+                                    GlobalTransform::default(),
+                                    Visibility::default(),
+                                )).id();
+                                selected.0 = Some(entity);
+                            }
+                        }
+                        format!("Opened {}", file.path)
+                    }
+                    Err(e) => format!("Open failed: {e}"),
+                };
             }
+        });
 
-            ui.separator();
-            ui.label("Tip: Palette cycles are applied with the `'` operator in the L-system.");
+        if !file.status.is_empty() {
+            ui.label(&file.status);
         }
     });
 }
@@ -0,0 +1,3 @@
+pub mod variation;
+
+pub use variation::{Distribution, Jitter, SymbolVariation, VariationProfile};
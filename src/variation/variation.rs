@@ -0,0 +1,86 @@
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Shape of the noise added by a `Jitter`
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Distribution {
+    /// Evenly spread over ±amount
+    #[default]
+    Uniform,
+    /// Gaussian with standard deviation `amount`
+    Normal,
+}
+
+/// Random offset applied to one geometric quantity
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Jitter {
+    #[serde(default)]
+    pub distribution: Distribution,
+    #[serde(default)]
+    pub amount: f32,
+}
+
+impl Jitter {
+    /// Draw an offset centred on zero
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        if self.amount <= 0.0 {
+            return 0.0;
+        }
+        match self.distribution {
+            Distribution::Uniform => rng.random_range(-self.amount..=self.amount),
+            Distribution::Normal => {
+                // Box-Muller transform
+                let u1: f32 = rng.random_range(f32::EPSILON..1.0);
+                let u2: f32 = rng.random();
+                self.amount * (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+            }
+        }
+    }
+
+    /// Multiplier around 1.0, for quantities given as a fraction (never negative)
+    pub fn sample_factor(&self, rng: &mut impl Rng) -> f32 {
+        (1.0 + self.sample(rng)).max(0.0)
+    }
+}
+
+/// Geometric noise for one symbol; each field only matters for symbols that use it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SymbolVariation {
+    pub symbol: char,
+    /// Fraction of the step size (`F`, `f`)
+    #[serde(default)]
+    pub step_length: Jitter,
+    /// Degrees added to the turn (`+ - & ^ \ /`)
+    #[serde(default)]
+    pub turn_angle: Jitter,
+    /// Fraction of the decay factor (`!`)
+    #[serde(default)]
+    pub thickness_decay: Jitter,
+    /// Fraction of the leaf size, sampled once per leaf (`{`)
+    #[serde(default)]
+    pub leaf_scale: Jitter,
+}
+
+impl SymbolVariation {
+    pub fn new(symbol: char) -> Self {
+        Self {
+            symbol,
+            step_length: Jitter::default(),
+            turn_angle: Jitter::default(),
+            thickness_decay: Jitter::default(),
+            leaf_scale: Jitter::default(),
+        }
+    }
+}
+
+/// Per-symbol geometric noise used by the interpreter, so one grammar can grow a varied forest
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct VariationProfile {
+    pub symbols: Vec<SymbolVariation>,
+}
+
+impl VariationProfile {
+    pub fn get(&self, symbol: char) -> Option<&SymbolVariation> {
+        self.symbols.iter().find(|v| v.symbol == symbol)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod wind;
+
+pub use wind::{Wind, WindExtension, WindMaterial, WindPlugin};
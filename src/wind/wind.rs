@@ -0,0 +1,158 @@
+use bevy::{
+    mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef},
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+    prelude::*,
+    render::render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderType, SpecializedMeshPipelineError, VertexFormat},
+    shader::ShaderRef,
+};
+
+use crate::skeleton::PlantSkeleton;
+
+const SHADER_ASSET_PATH: &str = "shaders/wind.wgsl";
+
+/// Number of branch points between the root and this vertex
+pub const ATTRIBUTE_BRANCH_DEPTH: MeshVertexAttribute =
+    MeshVertexAttribute::new("BranchDepth", 742_180_301, VertexFormat::Float32);
+/// Arc length from the branch pivot to this vertex
+pub const ATTRIBUTE_BRANCH_DISTANCE: MeshVertexAttribute =
+    MeshVertexAttribute::new("BranchDistance", 742_180_302, VertexFormat::Float32);
+/// Point the branch sways about, in mesh space
+pub const ATTRIBUTE_BRANCH_PIVOT: MeshVertexAttribute =
+    MeshVertexAttribute::new("BranchPivot", 742_180_303, VertexFormat::Float32x3);
+/// Per-branch phase offset in radians so neighbours don't move in lockstep
+pub const ATTRIBUTE_BRANCH_PHASE: MeshVertexAttribute =
+    MeshVertexAttribute::new("BranchPhase", 742_180_304, VertexFormat::Float32);
+
+/// Global wind applied to every plant
+#[derive(Resource, Clone, Copy)]
+pub struct Wind {
+    /// Horizontal direction the wind blows towards
+    pub direction: Vec3,
+    pub strength: f32,
+    /// 0 is a steady breeze; higher values add travelling gusts
+    pub gustiness: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self { direction: Vec3::X, strength: 1.0, gustiness: 0.5 }
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, Reflect, ShaderType)]
+pub struct WindUniform {
+    pub direction: Vec3,
+    pub strength: f32,
+    pub gustiness: f32,
+    /// 1 for leaves and petals, 0 for bark
+    pub flutter: f32,
+    /// Plant height, so the trunk bend is independent of plant size
+    pub height: f32,
+}
+
+/// Sways `StandardMaterial` geometry using the branch attributes above.
+/// Shadows and other prepasses still use the rest pose.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+pub struct WindExtension {
+    #[uniform(100)]
+    pub wind: WindUniform,
+}
+
+pub type WindMaterial = ExtendedMaterial<StandardMaterial, WindExtension>;
+
+impl WindExtension {
+    pub fn new(wind: &Wind, flutter: bool, height: f32) -> Self {
+        let mut extension = Self {
+            wind: WindUniform { flutter: if flutter { 1.0 } else { 0.0 }, height, ..default() },
+        };
+        extension.set_wind(wind);
+        extension
+    }
+
+    fn set_wind(&mut self, wind: &Wind) {
+        self.wind.direction = Vec3::new(wind.direction.x, 0.0, wind.direction.z).normalize_or(Vec3::X);
+        self.wind.strength = wind.strength;
+        self.wind.gustiness = wind.gustiness;
+    }
+}
+
+impl MaterialExtension for WindExtension {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // Same mesh buffer as the standard attributes, so just append ours to it
+        let branch = layout.0.get_layout(&[
+            ATTRIBUTE_BRANCH_DEPTH.at_shader_location(8),
+            ATTRIBUTE_BRANCH_DISTANCE.at_shader_location(9),
+            ATTRIBUTE_BRANCH_PIVOT.at_shader_location(10),
+            ATTRIBUTE_BRANCH_PHASE.at_shader_location(11),
+        ])?;
+        if let Some(buffer) = descriptor.vertex.buffers.first_mut() {
+            buffer.attributes.extend(branch.attributes);
+        }
+        Ok(())
+    }
+}
+
+/// Hierarchy data for one segment
+#[derive(Clone, Copy)]
+pub(crate) struct Sway {
+    pub(crate) depth: f32,
+    pub(crate) pivot: Vec3,
+    /// Arc length from the pivot to the segment's start
+    pub(crate) along: f32,
+    pub(crate) phase: f32,
+}
+
+/// Sway data per skeleton node: each lateral branch pivots about its base, one level deeper than its parent
+pub(crate) fn branch_sway(skeleton: &PlantSkeleton) -> Vec<Sway> {
+    let mut sway: Vec<Sway> = Vec::with_capacity(skeleton.nodes.len());
+    let mut branches = 0u32;
+    for (i, node) in skeleton.nodes.iter().enumerate() {
+        let entry = match node.parent {
+            Some(parent) if skeleton.continues_parent(i) => {
+                let p = sway[parent];
+                Sway { along: p.along + skeleton.nodes[parent].length(), ..p }
+            }
+            _ => {
+                branches += 1;
+                Sway {
+                    depth: node.order as f32,
+                    pivot: node.start,
+                    along: 0.0,
+                    // Golden-ratio steps spread phases evenly however many branches there are
+                    phase: (branches as f32 * 0.618_034).fract() * std::f32::consts::TAU,
+                }
+            }
+        };
+        sway.push(entry);
+    }
+    sway
+}
+
+/// Push the new wind settings into every plant material
+fn apply_wind(wind: Res<Wind>, mut materials: ResMut<Assets<WindMaterial>>) {
+    if !wind.is_changed() {
+        return;
+    }
+    for (_, material) in materials.iter_mut() {
+        material.extension.set_wind(&wind);
+    }
+}
+
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<WindMaterial>::default())
+            .init_resource::<Wind>()
+            .add_systems(Update, apply_wind);
+    }
+}